@@ -0,0 +1,37 @@
+//! Throughput of `dbgbb!(...)` with the buffer enabled and of `dbgbb_acc!(...)`, called from multiple threads.
+//!
+//! Run with `cargo bench --bench scaling` while the server is running.
+use dbgbb::*;
+use std::time::Instant;
+
+const POSTS: usize = 20_000;
+
+fn main() {
+    let _buf = Buffer::on();
+    for threads in [1, 2, 4, 8] {
+        let start = Instant::now();
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                std::thread::spawn(move || {
+                    let data = vec![1f64; 16];
+                    for i in 0..POSTS / threads {
+                        dbgbb!(every => 10, data.rename("bench_data"));
+                        dbgbb!(i.rename("bench_index"));
+                        dbgbb_acc!(label => "bench", keep_last => 100, i.rename("bench_acc"));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        dbgbb_acc!("bench" => post);
+        let elapsed = start.elapsed();
+        // Three macro calls per iteration, of which `every => 10` sends only every tenth.
+        let calls = 3 * threads * (POSTS / threads);
+        println!(
+            "{threads} thread(s): {:.0} calls/s",
+            calls as f64 / elapsed.as_secs_f64()
+        );
+    }
+}
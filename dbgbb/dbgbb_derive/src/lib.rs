@@ -0,0 +1,92 @@
+//! Derive macro for [dbgbb](https://crates.io/crates/dbgbb).
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Index, LitStr, parse_macro_input, parse_quote};
+
+/// Post a struct field by field. Each field is sent under the title `name.field`. The type parameters are required to implement `DbgBB`.
+///
+/// Attributes:
+/// - `#[dbgbb(skip)]` does not send the field.
+/// - `#[dbgbb(rename = "...")]` changes the name of the field in the title.
+#[proc_macro_derive(DbgBB, attributes(dbgbb))]
+pub fn derive_dbgbb(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "DbgBB can only be derived for structs.",
+        ));
+    };
+    let mut posts = vec![];
+    for (i, field) in data.fields.iter().enumerate() {
+        let mut skip = false;
+        let mut name = match &field.ident {
+            Some(ident) => ident.to_string(),
+            None => i.to_string(),
+        };
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("dbgbb"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    skip = true;
+                    Ok(())
+                } else if meta.path.is_ident("rename") {
+                    name = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else {
+                    Err(meta.error("Unknown attribute of DbgBB."))
+                }
+            })?;
+        }
+        if skip {
+            continue;
+        }
+        let member = match &field.ident {
+            Some(ident) => quote!(#ident),
+            None => {
+                let index = Index::from(i);
+                quote!(#index)
+            }
+        };
+        posts.push(quote! {
+            (&&dbgbb::Wrap(&self.#member)).dbgbb_objects(format!("{}.{}", title, #name), objs);
+        });
+    }
+    let (title, objs) = if posts.is_empty() {
+        (quote!(_title), quote!(_objs))
+    } else {
+        (quote!(title), quote!(objs))
+    };
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    // The fields of the generic types are sent through their own implementations.
+    let mut bounded = input.generics.clone();
+    for param in input.generics.type_params() {
+        let param = &param.ident;
+        bounded
+            .make_where_clause()
+            .predicates
+            .push(parse_quote!(#param: dbgbb::DbgBB));
+    }
+    let dbgbb_where_clause = &bounded.where_clause;
+    Ok(quote! {
+        impl #impl_generics dbgbb::DbgBB for #ident #ty_generics #dbgbb_where_clause {
+            #[allow(unused_imports)]
+            fn objects(&self, #title: &str, #objs: &mut Vec<(String, String, dbgbb::ArrayObject)>) {
+                use dbgbb::{ViaDbgBB, ViaRef, ViaTryInto};
+                #(#posts)*
+            }
+        }
+        impl #impl_generics dbgbb::Rename for #ident #ty_generics #where_clause {}
+    })
+}
@@ -0,0 +1,394 @@
+use crate::reduce::{Reduce, Reduction, to_reals};
+use crate::sampling::reservoir_slot;
+use crate::{lock, post};
+use array_object::{ArrayObject, TryConcat};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, LazyLock, Mutex};
+
+/// (label, title, tag)
+pub type AccKey = (String, String, String);
+
+#[doc(hidden)]
+pub static DATA_ACC: LazyLock<Mutex<HashMap<AccKey, Accumulator>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Operation of `dbgbb_acc!(...)` staged in the calling thread.
+enum Staged {
+    Push(ArrayObject, Option<usize>),
+    /// The values are converted to reals in advance so that nothing panics while `DATA_ACC` is locked.
+    Reduce(Reduce, Vec<f64>, Vec<usize>),
+    /// The slot of the reservoir of the given size is picked when applied, so that the count restarts after a post.
+    Sample(ArrayObject, usize),
+}
+
+type Stage = Arc<Mutex<Vec<(AccKey, Staged)>>>;
+
+/// Number of the staged operations, above which the stage of the thread is applied to `DATA_ACC`.
+const STAGE_LEN: usize = 64;
+
+/// Stages of all the threads, which are applied before `DATA_ACC` is read.
+static STAGES: Mutex<Vec<Stage>> = Mutex::new(Vec::new());
+
+thread_local! {
+    static STAGE: Stage = {
+        let stage = Stage::default();
+        lock(&STAGES).push(stage.clone());
+        stage
+    };
+}
+
+/// Stages the operations in the calling thread, so that the threads do not contend for `DATA_ACC` on every call.
+fn stage(ops: Vec<(AccKey, Staged)>, apply_now: bool) {
+    let mut ops = Some(ops);
+    let _ = STAGE.try_with(|stage| {
+        let mut staged = lock(stage);
+        staged.extend(ops.take().unwrap());
+        if apply_now || staged.len() >= STAGE_LEN {
+            apply(&mut staged);
+        }
+    });
+    // The thread-local stage is gone while the thread exits.
+    if let Some(mut ops) = ops {
+        apply(&mut ops);
+    }
+}
+
+/// Applies the staged operations in order. The stage is locked by the caller, so the order of the operations of a thread is kept.
+fn apply(staged: &mut Vec<(AccKey, Staged)>) {
+    if staged.is_empty() {
+        return;
+    }
+    let mut map = lock(&DATA_ACC);
+    for (key, op) in staged.drain(..) {
+        let acc = map.entry(key).or_default();
+        match op {
+            Staged::Push(obj, keep_last) => {
+                acc.keep_last = keep_last;
+                acc.push(obj);
+            }
+            Staged::Reduce(op, values, shape) => acc
+                .reduction
+                .get_or_insert_with(|| Reduction::new(op))
+                .update(values, shape),
+            Staged::Sample(obj, size) => {
+                acc.sampled = true;
+                if let Some(slot) = reservoir_slot(acc.seen, size) {
+                    acc.replace(slot, obj);
+                }
+                acc.seen += 1;
+            }
+        }
+    }
+}
+
+/// Applies the stages of all the threads. The stages of the finished threads are dropped.
+fn apply_all() {
+    let stages = {
+        let mut stages = lock(&STAGES);
+        stages.retain(|stage| Arc::strong_count(stage) > 1 || !lock(stage).is_empty());
+        stages.clone()
+    };
+    for stage in stages {
+        apply(&mut lock(&stage));
+    }
+}
+
+/// Total size in bytes of the accumulated data, after which the data is sent in chunks.
+static ACC_CAP: LazyLock<Option<usize>> =
+    LazyLock::new(|| std::env::var("BB_ACC_CAP").ok()?.parse().ok());
+
+/// Accumulated data of a single (label, title, tag).
+#[derive(Default)]
+pub struct Accumulator {
+    entries: VecDeque<ArrayObject>,
+    keep_last: Option<usize>,
+    sampled: bool,
+    /// Number of the values offered to the reservoir since the entries were taken.
+    seen: u64,
+    reduction: Option<Reduction>,
+    bytes: usize,
+    chunks: usize,
+}
+
+impl Accumulator {
+    fn push(&mut self, obj: ArrayObject) {
+        if let Some(n) = self.keep_last {
+            if n == 0 {
+                return;
+            }
+            while self.entries.len() >= n {
+                self.pop_front();
+            }
+        }
+        self.bytes += obj.datasize();
+        self.entries.push_back(obj);
+    }
+    fn pop_front(&mut self) {
+        if let Some(obj) = self.entries.pop_front() {
+            self.bytes -= obj.datasize();
+        }
+    }
+    fn replace(&mut self, slot: usize, obj: ArrayObject) {
+        self.bytes += obj.datasize();
+        match self.entries.get_mut(slot) {
+            Some(entry) => self.bytes -= std::mem::replace(entry, obj).datasize(),
+            None => self.entries.push_back(obj),
+        }
+    }
+    fn len(&self) -> usize {
+        match &self.reduction {
+            Some(reduction) => reduction.count() as usize,
+            None => self.entries.len(),
+        }
+    }
+    fn size(&self) -> usize {
+        match &self.reduction {
+            Some(reduction) => reduction.bytes(),
+            None => self.bytes,
+        }
+    }
+    /// Takes the entries out in the order of accumulation.
+    fn take(&mut self) -> Vec<ArrayObject> {
+        self.bytes = 0;
+        self.seen = 0;
+        self.entries.drain(..).collect()
+    }
+    /// Only the accumulation of all the entries is sent in chunks; the ring buffer and the reservoir are bounded anyway.
+    fn spillable(&self) -> bool {
+        self.keep_last.is_none() && !self.sampled && self.reduction.is_none()
+    }
+    /// Concatenates the entries. The chunk index is appended to the tag if the data has been sent in chunks.
+    fn concat(&mut self, key: &AccKey, last: bool) -> Vec<(String, String, ArrayObject)> {
+        if let Some(reduction) = self.reduction.take() {
+            return reduction
+                .result()
+                .map(|obj| (key.1.clone(), key.2.clone(), obj))
+                .into_iter()
+                .collect();
+        }
+        let entries = self.take();
+        if entries.is_empty() {
+            return vec![];
+        }
+        let tag = if last && self.chunks == 0 {
+            key.2.clone()
+        } else {
+            self.chunks += 1;
+            format!("{}:chunk{}", key.2, self.chunks - 1)
+        };
+        if is_uniform(&entries) {
+            vec![(key.1.clone(), tag, entries.try_concat().unwrap())]
+        } else {
+            // Ragged data, e.g. on an adaptive mesh, is sent as an indexed series.
+            entries
+                .into_iter()
+                .enumerate()
+                .map(|(i, obj)| (key.1.clone(), format!("{tag}:[{i}]"), obj))
+                .collect()
+        }
+    }
+}
+
+/// Returns true if the entries can be concatenated into an array of one higher dimension.
+pub(crate) fn is_uniform(entries: &[ArrayObject]) -> bool {
+    let first = &entries[0];
+    first.dimension() < 15
+        && entries.iter().all(|obj| {
+            obj.shape() == first.shape()
+                && obj.datatype() == first.datatype()
+                && obj.datasize() == first.datasize()
+        })
+}
+
+/// [Only for internal use] Helper function for `dbgbb_acc!(...)`. With `keep_last`, only the last entries are kept. With `cap`, the data of the label is sent in chunks when its size exceeds `cap` bytes.
+pub fn push_acc(objs: Vec<(AccKey, ArrayObject)>, keep_last: Option<usize>, cap: Option<usize>) {
+    if cap.is_none() && ACC_CAP.is_none() {
+        let ops = objs
+            .into_iter()
+            .map(|(key, obj)| (key, Staged::Push(obj, keep_last)))
+            .collect();
+        stage(ops, false);
+        return;
+    }
+    let labels: Vec<_> = objs.iter().map(|(key, _)| key.0.clone()).collect();
+    let ops = objs
+        .into_iter()
+        .map(|(key, obj)| (key, Staged::Push(obj, keep_last)))
+        .collect();
+    stage(ops, true);
+    let mut chunks = vec![];
+    {
+        let mut map = lock(&DATA_ACC);
+        if let Some(cap) = cap {
+            let mut checked = vec![];
+            for label in labels {
+                if checked.contains(&label) {
+                    continue;
+                }
+                let bytes: usize = map
+                    .iter()
+                    .filter(|(key, acc)| key.0 == label && acc.spillable())
+                    .map(|(_, acc)| acc.bytes)
+                    .sum();
+                if bytes > cap {
+                    spill(&mut map, Some(&label), &mut chunks);
+                }
+                checked.push(label);
+            }
+        }
+        if let Some(cap) = *ACC_CAP {
+            let bytes: usize = map
+                .values()
+                .filter(|acc| acc.spillable())
+                .map(|acc| acc.bytes)
+                .sum();
+            if bytes > cap {
+                spill(&mut map, None, &mut chunks);
+            }
+        }
+    }
+    post(chunks).unwrap();
+}
+
+fn spill(
+    map: &mut HashMap<AccKey, Accumulator>,
+    label: Option<&str>,
+    chunks: &mut Vec<(String, String, ArrayObject)>,
+) {
+    for (key, acc) in map.iter_mut() {
+        if label.is_none_or(|label| key.0 == label) && acc.spillable() {
+            chunks.extend(acc.concat(key, false));
+        }
+    }
+}
+
+/// [Only for internal use] Helper function for `dbgbb_acc!(..., reduce => op, ...)`. Complex numbers and strings are skipped except for `count`.
+pub fn reduce_acc(objs: Vec<(AccKey, ArrayObject)>, op: Reduce) {
+    let ops = objs
+        .into_iter()
+        .filter_map(|(key, obj)| {
+            let values = match op {
+                Reduce::Count => vec![],
+                _ => to_reals(&obj)?,
+            };
+            Some((key, Staged::Reduce(op, values, obj.shape())))
+        })
+        .collect();
+    stage(ops, false);
+}
+
+/// [Only for internal use] Helper function for `dbgbb_acc!(..., reservoir => k, ...)`.
+pub fn sample_acc(objs: Vec<(AccKey, ArrayObject)>, size: usize) {
+    let ops = objs
+        .into_iter()
+        .map(|(key, obj)| (key, Staged::Sample(obj, size)))
+        .collect();
+    stage(ops, false);
+}
+
+/// Accumulated data waiting to be sent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingAcc {
+    pub label: String,
+    pub title: String,
+    pub tag: String,
+    /// Number of the accumulated entries.
+    pub entries: usize,
+    /// Size of the accumulated data in bytes.
+    pub bytes: usize,
+}
+
+/// Lists the accumulated data, sorted by label, title and tag.
+pub fn pending_acc() -> Vec<PendingAcc> {
+    apply_all();
+    let map = lock(&DATA_ACC);
+    let mut list: Vec<_> = map
+        .iter()
+        .filter(|(_, acc)| acc.len() > 0)
+        .map(|(key, acc)| PendingAcc {
+            label: key.0.clone(),
+            title: key.1.clone(),
+            tag: key.2.clone(),
+            entries: acc.len(),
+            bytes: acc.size(),
+        })
+        .collect();
+    list.sort_by(|a, b| (&a.label, &a.title, &a.tag).cmp(&(&b.label, &b.title, &b.tag)));
+    list
+}
+
+/// Discards the accumulated data of the label without sending it.
+pub fn clear_acc(label: &str) {
+    apply_all();
+    lock(&DATA_ACC).retain(|key, _| key.0 != label);
+}
+
+/// Changes the label of the accumulated data. The data is appended if the new label already has data of the same title and tag.
+pub fn rename_acc(label: &str, new_label: &str) {
+    if label == new_label {
+        return;
+    }
+    apply_all();
+    let mut map = lock(&DATA_ACC);
+    let keys: Vec<_> = map.keys().filter(|key| key.0 == label).cloned().collect();
+    for key in keys {
+        let mut acc = map.remove(&key).unwrap();
+        let new_key = (new_label.to_string(), key.1, key.2);
+        match map.get_mut(&new_key) {
+            Some(dest) => {
+                for obj in acc.take() {
+                    dest.push(obj);
+                }
+                if let Some(reduction) = acc.reduction {
+                    match &mut dest.reduction {
+                        Some(dest) => dest.merge(reduction),
+                        None => dest.reduction = Some(reduction),
+                    }
+                }
+            }
+            None => {
+                map.insert(new_key, acc);
+            }
+        }
+    }
+}
+
+/// Sends the accumulated data of all the labels. This can also be called from an exit hook such as `atexit`.
+pub fn post_all_acc() {
+    post(take_all_acc()).unwrap();
+}
+
+fn take_all_acc() -> Vec<(String, String, ArrayObject)> {
+    apply_all();
+    let mut objs = vec![];
+    let mut map = lock(&DATA_ACC);
+    for (key, mut acc) in map.drain() {
+        objs.extend(acc.concat(&key, true));
+    }
+    objs
+}
+
+/// Sends the accumulated data of all the labels when a thread panics, e.g. the last states kept by `keep_last => n`. The panic hook set before, such as the default one printing the message, is called first.
+pub fn post_acc_on_panic() {
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        hook(info);
+        // A panic in the hook would abort the process.
+        let _ = post(take_all_acc());
+    }));
+}
+
+/// Sends the accumulated data of the label. Same as `dbgbb_acc!(label => post)`.
+pub fn post_acc(label: &str) {
+    apply_all();
+    let mut objs = vec![];
+    {
+        let mut map = lock(&DATA_ACC);
+        let keys: Vec<_> = map.keys().filter(|key| key.0 == label).cloned().collect();
+        for key in keys {
+            let mut acc = map.remove(&key).unwrap();
+            objs.extend(acc.concat(&key, true));
+        }
+    }
+    post(objs).unwrap();
+}
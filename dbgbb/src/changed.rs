@@ -0,0 +1,20 @@
+use crate::reduce::to_floats;
+use array_object::{ArrayObject, DataType};
+
+/// Compares the values for `dbgbb!(changed => tol, ...)`. Floats are compared with the absolute tolerance `tol`.
+pub(crate) fn has_changed(prev: &ArrayObject, obj: &ArrayObject, tol: f64) -> bool {
+    if tol <= 0.0
+        || prev.shape() != obj.shape()
+        || prev.datatype() != obj.datatype()
+        || !matches!(obj.datatype(), DataType::Real | DataType::Complex)
+    {
+        return prev != obj;
+    }
+    match (to_floats(prev), to_floats(obj)) {
+        (Some(a), Some(b)) => a
+            .iter()
+            .zip(b.iter())
+            .any(|(a, b)| (a - b).abs() > tol || a.is_nan() != b.is_nan()),
+        _ => prev != obj,
+    }
+}
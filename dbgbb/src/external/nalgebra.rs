@@ -1,6 +1,45 @@
-use crate::Rename;
+use crate::nested::split_axes;
+use crate::{Axes, DbgBB, Element, Inspect, Nested, Rename};
+use array_object::ArrayObject;
 use nalgebra::base::dimension::Dim;
 use nalgebra::base::storage::RawStorage;
 use nalgebra::base::Matrix;
 
 impl<R: Dim, C: Dim, T, S: RawStorage<T, R, C>> Rename for Matrix<T, R, C, S> {}
+
+impl<R: Dim, C: Dim, T: Inspect, S: RawStorage<T, R, C>> Inspect for Matrix<T, R, C, S> {
+    fn has_nonfinite(&self) -> bool {
+        self.iter().any(|x| x.has_nonfinite())
+    }
+    fn exceeds(&self, bound: f64) -> bool {
+        self.iter().any(|x| x.exceeds(bound))
+    }
+}
+
+impl<R: Dim, C: Dim, T, S: RawStorage<T, R, C>> Nested for Matrix<T, R, C, S>
+where
+    for<'a> &'a Matrix<T, R, C, S>: TryInto<ArrayObject, Error: std::fmt::Debug>,
+{
+    fn to_object(&self) -> ArrayObject {
+        self.try_into().unwrap()
+    }
+}
+
+impl<R: Dim, C: Dim, T: Element, S: RawStorage<T, R, C>> Axes for Matrix<T, R, C, S> {
+    fn visit_axes(&self, axes: &[usize], f: &mut dyn FnMut(&[usize], ArrayObject)) {
+        let (rows, cols) = self.shape();
+        let data: Vec<_> = (0..rows)
+            .flat_map(|i| (0..cols).map(move |j| self[(i, j)].clone()))
+            .collect();
+        split_axes(&data, &[rows, cols], axes, f);
+    }
+}
+
+impl<R: Dim, C: Dim, T, S: RawStorage<T, R, C>> DbgBB for Matrix<T, R, C, S>
+where
+    for<'a> &'a Matrix<T, R, C, S>: TryInto<ArrayObject, Error: std::fmt::Debug>,
+{
+    fn objects(&self, title: &str, objs: &mut Vec<(String, String, ArrayObject)>) {
+        objs.push((title.to_string(), String::new(), self.try_into().unwrap()));
+    }
+}
@@ -3,7 +3,44 @@ use ndarray_15 as ndarray;
 #[cfg(feature = "ndarray_16")]
 use ndarray_16 as ndarray;
 
-use crate::Rename;
-use ndarray::{Array, Dimension};
+use crate::nested::split_axes;
+use crate::{Axes, DbgBB, Element, Inspect, Nested, Rename};
+use array_object::ArrayObject;
+use ndarray::{ArrayBase, Data, Dimension, RawData};
 
-impl<T, D: Dimension> Rename for Array<T, D> {}
+impl<S: RawData, D: Dimension> Rename for ArrayBase<S, D> {}
+
+impl<T: Inspect, S: Data<Elem = T>, D: Dimension> Inspect for ArrayBase<S, D> {
+    fn has_nonfinite(&self) -> bool {
+        self.iter().any(|x| x.has_nonfinite())
+    }
+    fn exceeds(&self, bound: f64) -> bool {
+        self.iter().any(|x| x.exceeds(bound))
+    }
+}
+
+impl<T: Element, S: Data<Elem = T>, D: Dimension> Nested for ArrayBase<S, D> {
+    fn to_object(&self) -> ArrayObject {
+        to_object(self)
+    }
+}
+
+impl<T: Element, S: Data<Elem = T>, D: Dimension> Axes for ArrayBase<S, D> {
+    fn visit_axes(&self, axes: &[usize], f: &mut dyn FnMut(&[usize], ArrayObject)) {
+        let data: Vec<_> = self.iter().cloned().collect();
+        split_axes(&data, self.shape(), axes, f);
+    }
+}
+
+/// Arrays of `bool` are sent as `u8`.
+impl<T: Element, S: Data<Elem = T>, D: Dimension> DbgBB for ArrayBase<S, D> {
+    fn objects(&self, title: &str, objs: &mut Vec<(String, String, ArrayObject)>) {
+        objs.push((title.to_string(), String::new(), to_object(self)));
+    }
+}
+
+/// Views of any strides are sent in the logical row-major order.
+fn to_object<T: Element, S: Data<Elem = T>, D: Dimension>(array: &ArrayBase<S, D>) -> ArrayObject {
+    let shape = array.shape().iter().map(|&n| n as u64).collect();
+    T::to_object(array.iter().cloned().collect(), shape)
+}
@@ -0,0 +1,31 @@
+use std::sync::Once;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static FORKS: AtomicU64 = AtomicU64::new(0);
+static REGISTER: Once = Once::new();
+
+#[cfg(unix)]
+unsafe extern "C" {
+    fn pthread_atfork(
+        prepare: Option<unsafe extern "C" fn()>,
+        parent: Option<unsafe extern "C" fn()>,
+        child: Option<unsafe extern "C" fn()>,
+    ) -> std::ffi::c_int;
+}
+
+#[cfg(unix)]
+unsafe extern "C" fn on_fork_child() {
+    FORKS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Returns the number of forks between the first call and the current process.
+pub(crate) fn forks() -> u64 {
+    REGISTER.call_once(|| {
+        #[cfg(unix)]
+        // SAFETY: The handler only touches an atomic, which is async-signal-safe.
+        unsafe {
+            pthread_atfork(None, None, Some(on_fork_child));
+        }
+    });
+    FORKS.load(Ordering::Relaxed)
+}
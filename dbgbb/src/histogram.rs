@@ -0,0 +1,133 @@
+use crate::fork::forks;
+use crate::reduce::to_reals;
+use crate::sender::post_direct;
+use crate::{lock, post};
+use array_object::ArrayObject;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex, MutexGuard, Once};
+
+/// (title, tag)
+type HistKey = (String, String);
+
+static DATA_HIST: LazyLock<Mutex<HashMap<HistKey, Histogram>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static REGISTER: Once = Once::new();
+/// Number of the forks when the histograms were last accessed.
+static FORKS: AtomicU64 = AtomicU64::new(0);
+
+/// Locks the histograms. The histograms inherited from the parent process are discarded in a forked child, so that the child does not send them again at exit.
+fn lock_hist() -> MutexGuard<'static, HashMap<HistKey, Histogram>> {
+    let mut map = lock(&DATA_HIST);
+    let forks = forks();
+    if FORKS.swap(forks, Ordering::Relaxed) != forks {
+        map.clear();
+    }
+    map
+}
+
+unsafe extern "C" {
+    fn atexit(callback: extern "C" fn()) -> std::ffi::c_int;
+}
+
+extern "C" fn on_exit() {
+    // A panic must not unwind into the C runtime.
+    let _ = std::panic::catch_unwind(|| post_direct(take_hist()));
+}
+
+/// Histogram binned in the process.
+struct Histogram {
+    lo: f64,
+    hi: f64,
+    log: bool,
+    counts: Vec<u64>,
+    underflow: u64,
+    overflow: u64,
+}
+
+impl Histogram {
+    fn new(bins: usize, (lo, hi): (f64, f64), log: bool) -> Self {
+        assert!(bins > 0, "The number of bins must be positive.");
+        assert!(lo < hi, "The range of the histogram is empty.");
+        assert!(!log || lo > 0.0, "The range of log bins must be positive.");
+        let (lo, hi) = if log { (lo.ln(), hi.ln()) } else { (lo, hi) };
+        Self {
+            lo,
+            hi,
+            log,
+            counts: vec![0; bins],
+            underflow: 0,
+            overflow: 0,
+        }
+    }
+    /// NaN is ignored. The upper end of the range falls into the last bin.
+    fn fill(&mut self, x: f64) {
+        if x.is_nan() {
+            return;
+        }
+        // Non-positive values are underflows of log bins.
+        let x = if self.log { x.max(0.0).ln() } else { x };
+        if x < self.lo {
+            self.underflow += 1;
+        } else if x > self.hi {
+            self.overflow += 1;
+        } else {
+            let bins = self.counts.len();
+            let i = ((x - self.lo) / (self.hi - self.lo) * bins as f64) as usize;
+            self.counts[i.min(bins - 1)] += 1;
+        }
+    }
+    fn edges(&self) -> Vec<f64> {
+        let bins = self.counts.len();
+        (0..=bins)
+            .map(|i| {
+                let edge = self.lo + (self.hi - self.lo) * i as f64 / bins as f64;
+                if self.log { edge.exp() } else { edge }
+            })
+            .collect()
+    }
+}
+
+/// [Only for internal use] Helper function for `dbgbb_hist!(...)`. The bins and the range of the first call are used.
+pub fn fill_hist(
+    title: String,
+    tag: String,
+    obj: &ArrayObject,
+    bins: usize,
+    range: (f64, f64),
+    log: bool,
+) {
+    REGISTER.call_once(|| {
+        // SAFETY: The callback catches panics and only sends the histograms.
+        unsafe {
+            atexit(on_exit);
+        }
+    });
+    let values = to_reals(obj).expect("Only integers and real numbers can be binned.");
+    let mut map = lock_hist();
+    let hist = map
+        .entry((title, tag))
+        .or_insert_with(|| Histogram::new(bins, range, log));
+    for x in values {
+        hist.fill(x);
+    }
+}
+
+/// Sends the histograms and starts them afresh. The bin edges, the counts and [underflow, overflow] are sent with `:edges`, `:counts` and `:outliers` appended to the tag. This is also called at exit.
+pub fn post_hist() {
+    post(take_hist()).unwrap();
+}
+
+fn take_hist() -> Vec<(String, String, ArrayObject)> {
+    let mut objs = vec![];
+    for ((title, tag), hist) in lock_hist().drain() {
+        objs.push((title.clone(), format!("{tag}:edges"), hist.edges().into()));
+        objs.push((title.clone(), format!("{tag}:counts"), hist.counts.into()));
+        objs.push((
+            title,
+            format!("{tag}:outliers"),
+            vec![hist.underflow, hist.overflow].into(),
+        ));
+    }
+    objs
+}
@@ -0,0 +1,98 @@
+use crate::rename::Renamed;
+use num_complex::Complex;
+use std::collections::VecDeque;
+
+/// Inspect the values for the conditional capture.
+pub trait Inspect {
+    /// Returns true if any element is NaN or infinite.
+    fn has_nonfinite(&self) -> bool;
+    /// Returns true if the absolute value of any element exceeds the bound or is NaN.
+    fn exceeds(&self, bound: f64) -> bool;
+}
+
+macro_rules! impl_inspect_float {
+    ($($ty:ty),*) => {
+        $(
+            impl Inspect for $ty {
+                fn has_nonfinite(&self) -> bool {
+                    !self.is_finite()
+                }
+                fn exceeds(&self, bound: f64) -> bool {
+                    self.is_nan() || self.abs() as f64 > bound
+                }
+            }
+            impl Inspect for Complex<$ty> {
+                fn has_nonfinite(&self) -> bool {
+                    !self.is_finite()
+                }
+                fn exceeds(&self, bound: f64) -> bool {
+                    self.is_nan() || self.norm() as f64 > bound
+                }
+            }
+        )*
+    }
+}
+
+macro_rules! impl_inspect_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl Inspect for $ty {
+                fn has_nonfinite(&self) -> bool {
+                    false
+                }
+                fn exceeds(&self, bound: f64) -> bool {
+                    (*self as f64).abs() > bound
+                }
+            }
+        )*
+    }
+}
+
+impl_inspect_float!(f32, f64);
+
+impl_inspect_integer!(u8, u16, u32, u64, usize, i8, i16, i32, i64, i128, isize);
+
+impl<T: Inspect> Inspect for [T] {
+    fn has_nonfinite(&self) -> bool {
+        self.iter().any(|x| x.has_nonfinite())
+    }
+    fn exceeds(&self, bound: f64) -> bool {
+        self.iter().any(|x| x.exceeds(bound))
+    }
+}
+
+impl<T: Inspect> Inspect for Vec<T> {
+    fn has_nonfinite(&self) -> bool {
+        self.as_slice().has_nonfinite()
+    }
+    fn exceeds(&self, bound: f64) -> bool {
+        self.as_slice().exceeds(bound)
+    }
+}
+
+impl<T: Inspect> Inspect for VecDeque<T> {
+    fn has_nonfinite(&self) -> bool {
+        self.iter().any(|x| x.has_nonfinite())
+    }
+    fn exceeds(&self, bound: f64) -> bool {
+        self.iter().any(|x| x.exceeds(bound))
+    }
+}
+
+impl<T: Inspect, const N: usize> Inspect for [T; N] {
+    fn has_nonfinite(&self) -> bool {
+        self.as_slice().has_nonfinite()
+    }
+    fn exceeds(&self, bound: f64) -> bool {
+        self.as_slice().exceeds(bound)
+    }
+}
+
+impl<T: Inspect> Inspect for Renamed<T> {
+    fn has_nonfinite(&self) -> bool {
+        self.data.has_nonfinite()
+    }
+    fn exceeds(&self, bound: f64) -> bool {
+        self.data.exceeds(bound)
+    }
+}
@@ -1,10 +1,41 @@
 //! # dbgbb!
 //!
 //! A framework for analyzing debugging data in a Mathematica/Jupyter notebook.
+
+/// Forwards a trait through the smart pointers and `Cow`. Defined before the modules that use it.
+macro_rules! impl_pointer {
+    ($trait:ident $body:tt) => {
+        impl_pointer!(@ptr $trait $body, Box, Rc, Arc);
+        impl<B: $trait + ToOwned + ?Sized> $trait for Cow<'_, B> $body
+    };
+    (@ptr $trait:ident $body:tt, $($ptr:ident),*) => {
+        $(
+            impl<T: $trait + ?Sized> $trait for $ptr<T> $body
+        )*
+    };
+}
+
+mod accumulator;
+mod changed;
 mod external;
+mod fork;
+mod histogram;
+mod inspect;
+mod nested;
+mod objects;
 mod reader;
+mod reduce;
 mod rename;
+mod sampling;
 mod sender;
+mod site;
+mod timer;
+
+#[doc(hidden)]
+pub use accumulator::{DATA_ACC, push_acc, reduce_acc, sample_acc};
+pub use accumulator::{
+    PendingAcc, clear_acc, pending_acc, post_acc, post_acc_on_panic, post_all_acc, rename_acc,
+};
 
 #[doc(hidden)]
 pub use array_object::{ArrayObject, Pack, TryConcat};
@@ -12,27 +43,44 @@ pub use array_object::{ArrayObject, Pack, TryConcat};
 #[doc(hidden)]
 pub use bulletin_board_common::*;
 
+pub use dbgbb_derive::DbgBB;
 #[doc(hidden)]
-pub use reader::read_bulletin;
+pub use histogram::fill_hist;
+pub use histogram::post_hist;
+pub use inspect::Inspect;
+pub use nested::{Axes, Nested};
+#[doc(hidden)]
+pub use nested::{post_axes, post_nested};
+pub use objects::DbgBB;
+#[doc(hidden)]
+pub use objects::{Element, ViaDbgBB, ViaRef, ViaTryInto, Wrap};
+#[doc(hidden)]
+pub use reader::{read_bulletin, read_map, read_mask};
+#[doc(hidden)]
+pub use reduce::Reduce;
 pub use rename::Rename;
+#[doc(hidden)]
+pub use sampling::log_step;
 pub use sender::Buffer;
 
 #[doc(hidden)]
-pub use sender::SENDER;
-
-use std::collections::HashMap;
-use std::sync::{LazyLock, Mutex};
-
+pub use sender::{SENDER, post};
 #[doc(hidden)]
-pub static COUNTER: LazyLock<Mutex<HashMap<String, u64>>> =
-    LazyLock::new(|| Mutex::new(HashMap::new()));
+pub use site::Site;
+pub use timer::Timer;
 
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// [Only for internal use] Locks the global state. A mutex poisoned by a panic in another thread is recovered so that the debug output keeps working.
 #[doc(hidden)]
-pub static DATA_ACC: LazyLock<Mutex<HashMap<(String, String, String), Vec<ArrayObject>>>> =
-    LazyLock::new(|| Mutex::new(HashMap::new()));
+pub fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
 
 /// Send the debug data to the server.
 ///
+/// Like `dbg!(...)`, the macro returns the value of the argument, or a tuple of the values for multiple arguments, by move. Pass a reference to keep using the variable; the leading `&` is removed from the title. A renamed value is returned as it is, and `.into_inner()` takes the original value back.
+///
 /// Usage:
 /// ```
 /// use dbgbb::dbgbb;
@@ -41,41 +89,167 @@ pub static DATA_ACC: LazyLock<Mutex<HashMap<(String, String, String), Vec<ArrayO
 ///         dbgbb!(a, b);
 ///         dbgbb!(every => 3, a, b);
 ///         dbgbb!(oneshot => 5, a, b);
+///         dbgbb!(logevery => 2, a, b);
 ///     }
 /// }
+/// let v = vec![1f64, 2., 3.];
+/// dbgbb!(&v);
+/// let y: f64 = dbgbb!(v.iter().sum::<f64>() * 2.0);
+/// ```
+///
+/// Slices such as `&v[1..]`, `VecDeque<T>`, `Box<[T]>`, `Rc<[T]>`, `Arc<[T]>` and `Cow<[T]>` are sent like `Vec<T>`.
+///
+/// `logevery => n` captures the data at the 1st, n-th, n^2-th, ... call.
+///
+/// The data can also be captured only when a value of floats goes wrong:
+/// ```
+/// use dbgbb::dbgbb;
+/// let x = vec![1f64, f64::NAN];
+/// let (a, b) = (1, 2);
+/// dbgbb!(on_nonfinite => x, a, b); // Captures if x contains NaN or Inf.
+/// dbgbb!(on_nonfinite => x, once, a, b); // Captures only the first occurrence at this site.
+/// dbgbb!(on_exceed => (x, 1e3), a, b); // Captures if |x| > 1e3 or x contains NaN.
+/// dbgbb!(on_exceed => (x, 1e3), once, a, b);
+/// ```
+///
+/// To skip the values identical to the last ones sent from the same site:
+/// ```
+/// use dbgbb::dbgbb;
+/// for i in 0..10 {
+///     let a = i / 5;
+///     let b = i as f64 * 1e-12;
+///     dbgbb!(changed => 0, a); // Sent only twice.
+///     dbgbb!(changed => 1e-9, b); // Floats within the tolerance are regarded as unchanged.
+/// }
+/// ```
+///
+/// Structs with `#[derive(DbgBB)]` are sent field by field under the titles `name.field`, which also works with `dbgbb_acc!`:
 /// ```
+/// use dbgbb::{DbgBB, dbgbb};
+/// #[derive(Clone, DbgBB)]
+/// struct Inner {
+///     x: f64,
+/// }
+/// #[derive(Clone, DbgBB)]
+/// struct State {
+///     step: u64,
+///     inner: Inner, // Sent as state.inner.x
+///     #[dbgbb(rename = "temperature")]
+///     temp: f64,
+///     #[dbgbb(skip)]
+///     name: String,
+/// }
+/// let state = State { step: 0, inner: Inner { x: 1.0 }, temp: 300.0, name: "run".to_string() };
+/// dbgbb!(&state);
+/// ```
+///
+/// A `Vec` of such structs is sent as one array per field, e.g. `particles.pos` of the shape `[n, 3]` for `pos: [f64; 3]`. If the shape of a field varies, its values are sent one by one with the index `:[i]` appended to the tag.
+///
+/// Tuples are sent element by element as `name.0`, `name.1`, ..., while `Vec<(f64, f64)>` and `Vec<[f64; 3]>` are sent as arrays of the shapes `[n, 2]` and `[n, 3]`.
+///
+/// The values of `HashMap` and `BTreeMap` are sent under the title of the map with `:[key]` appended to the tag, and the list of the keys with `:keys`.
+///
+/// `Some` and `Ok` send the inner value, while `None` and `Err` are sent as strings with `:none` and `:err` appended to the tag.
 #[macro_export]
 macro_rules! dbgbb {
     ($($x:expr),*) => {{
-        let sender = dbgbb::SENDER.lock().unwrap();
-        use dbgbb::{Rename, Pack};
+        use dbgbb::{Rename, ViaDbgBB, ViaRef, ViaTryInto};
         let mut objs = vec![];
-        $(
-            let title = match $x.get_name() {
+        let ret = ($({
+            let x = $x;
+            let title = match x.get_name() {
                 Some(name) => name,
-                None => stringify!($x).to_string(),
+                None => stringify!($x).trim_start_matches('&').to_string(),
             };
             let tag = format!("{}:{}:{}", file!(), line!(), column!());
-            let obj: dbgbb::ArrayObject = $x.clone().try_into().unwrap();
-            objs.push((title, tag, obj));
-        )*
-        sender.post(objs).unwrap();
+            let mut fields = vec![];
+            (&&dbgbb::Wrap(&x)).dbgbb_objects(title, &mut fields);
+            for (title, suffix, obj) in fields {
+                objs.push((title, format!("{tag}{suffix}"), obj));
+            }
+            x
+        }),*);
+        dbgbb::post(objs).unwrap();
+        ret
+    }};
+    (changed => $tol:expr, $($x:expr),*) => {{
+        use dbgbb::{Rename, ViaDbgBB, ViaRef, ViaTryInto};
+        static SITE: dbgbb::Site = dbgbb::Site::new();
+        let mut objs = vec![];
+        let ret = ($({
+            let x = $x;
+            let title = match x.get_name() {
+                Some(name) => name,
+                None => stringify!($x).trim_start_matches('&').to_string(),
+            };
+            let tag = format!("{}:{}:{}", file!(), line!(), column!());
+            let mut fields = vec![];
+            (&&dbgbb::Wrap(&x)).dbgbb_objects(title, &mut fields);
+            for (title, suffix, obj) in fields {
+                if SITE.changed(&format!("{title}{suffix}"), &obj, $tol as f64) {
+                    objs.push((title, format!("{tag}{suffix}"), obj));
+                }
+            }
+            x
+        }),*);
+        dbgbb::post(objs).unwrap();
+        ret
     }};
     (every => $n:literal, $($x:expr),*) => {{
-        let mut map = dbgbb::COUNTER.lock().unwrap();
-        let count = map.entry(format!("{}:{}:{}", file!(), line!(), column!())).or_insert(0);
-        if *count % $n == 0 {
-            dbgbb!($($x),*);
+        static SITE: dbgbb::Site = dbgbb::Site::new();
+        if SITE.tick().is_multiple_of($n) {
+            dbgbb!($($x),*)
+        } else {
+            ($($x),*)
         }
-        *count += 1;
     }};
     (oneshot => $n:literal, $($x:expr),*) => {{
-        let mut map = dbgbb::COUNTER.lock().unwrap();
-        let count = map.entry(format!("{}:{}:{}", file!(), line!(), column!())).or_insert(0);
-        if *count == $n {
-            dbgbb!($($x),*);
+        static SITE: dbgbb::Site = dbgbb::Site::new();
+        if SITE.tick() == $n {
+            dbgbb!($($x),*)
+        } else {
+            ($($x),*)
+        }
+    }};
+    (logevery => $n:literal, $($x:expr),*) => {{
+        static SITE: dbgbb::Site = dbgbb::Site::new();
+        if dbgbb::log_step(SITE.tick() + 1, $n) {
+            dbgbb!($($x),*)
+        } else {
+            ($($x),*)
+        }
+    }};
+    (on_nonfinite => $v:expr, once, $($x:expr),*) => {{
+        use dbgbb::Inspect;
+        if $v.has_nonfinite() {
+            dbgbb!(oneshot => 0, $($x),*)
+        } else {
+            ($($x),*)
+        }
+    }};
+    (on_nonfinite => $v:expr, $($x:expr),*) => {{
+        use dbgbb::Inspect;
+        if $v.has_nonfinite() {
+            dbgbb!($($x),*)
+        } else {
+            ($($x),*)
+        }
+    }};
+    (on_exceed => ($v:expr, $bound:expr), once, $($x:expr),*) => {{
+        use dbgbb::Inspect;
+        if $v.exceeds($bound as f64) {
+            dbgbb!(oneshot => 0, $($x),*)
+        } else {
+            ($($x),*)
+        }
+    }};
+    (on_exceed => ($v:expr, $bound:expr), $($x:expr),*) => {{
+        use dbgbb::Inspect;
+        if $v.exceeds($bound as f64) {
+            dbgbb!($($x),*)
+        } else {
+            ($($x),*)
         }
-        *count += 1;
     }};
 }
 
@@ -88,52 +262,185 @@ macro_rules! dbgbb {
 ///     for b in 0..3 {
 ///         dbgbb_acc!(label => "i", a, b);
 ///         dbgbb_acc!(label => "j", every => 2, a, b);
+///         dbgbb_acc!(label => "k", reservoir => 4, a, b);
 ///     }
 /// }
 /// dbgbb_acc!("i" => post);
 /// dbgbb_acc!("j" => post);
+/// dbgbb_acc!("k" => post);
+/// ```
+///
+/// The values of the same title are concatenated into an array of one higher dimension. If their shapes differ, they are sent one by one with the index `:[i]` appended to the tag.
+///
+/// The accumulated data can be managed by [`pending_acc`], [`clear_acc`], [`rename_acc`], [`post_acc`] and [`post_all_acc`]. [`post_acc_on_panic`] sends all of it when a thread panics, keeping the panic message:
+/// ```
+/// dbgbb::post_acc_on_panic();
+/// ```
+///
+/// `reduce => op` keeps only the element-wise reduction over the calls in constant memory, where `op` is one of `sum`, `mean`, `min`, `max`, `var` (unbiased variance) and `count`. A change of the shape restarts the reduction except for `count`. Complex numbers and strings cannot be reduced and are skipped except for `count`:
+/// ```
+/// use dbgbb::dbgbb_acc;
+/// for step in 0..1000 {
+///     let residual = vec![1. / (step as f64 + 1.); 3];
+///     dbgbb_acc!(label => "stats", reduce => mean, residual);
+/// }
+/// dbgbb_acc!("stats" => post);
+/// ```
+///
+/// `reservoir => k` keeps a uniformly random sample of at most k calls since the last post. The order of the samples is not preserved.
+///
+/// `keep_last => n` keeps only the last n calls in a ring buffer, e.g. to inspect the states just before a failure:
+/// ```
+/// use dbgbb::dbgbb_acc;
+/// for step in 0..1000 {
+///     let state = vec![step as f64; 4];
+///     dbgbb_acc!(label => "last", keep_last => 100, state);
+/// }
+/// dbgbb_acc!("last" => post); // Steps 900..1000 in order.
+/// ```
+///
+/// To send the last states when the program panics instead, call [`post_acc_on_panic`] at the start.
+///
+/// `cap => bytes` bounds the memory used by the label. When the accumulated data exceeds the cap, it is sent as a chunk with `:chunk0`, `:chunk1`, ... appended to the tag, and the accumulation starts afresh. The environment variable `BB_ACC_CAP` sets the cap on the total of all the labels.
+/// ```
+/// use dbgbb::dbgbb_acc;
+/// for step in 0..1000 {
+///     let state = vec![step as f64; 1000];
+///     dbgbb_acc!(label => "capped", cap => 1 << 20, state);
+/// }
+/// dbgbb_acc!("capped" => post);
 /// ```
 #[macro_export]
 macro_rules! dbgbb_acc {
-    (label => $label:literal, $($x:expr),*) => {{
-        use dbgbb::Rename;
-        let mut map = dbgbb::DATA_ACC.lock().unwrap();
+    (@objs $label:literal, $($x:expr),*) => {{
+        use dbgbb::{Rename, ViaDbgBB, ViaRef, ViaTryInto};
+        let mut objs = vec![];
         $(
-            let title = match $x.get_name() {
+            let x = &$x;
+            let title = match x.get_name() {
                 Some(name) => name,
-                None => stringify!($x).to_string(),
+                None => stringify!($x).trim_start_matches('&').to_string(),
             };
             let tag = format!("{}:{}:{}", file!(), line!(), column!());
-            let entry = map.entry(($label.to_string(), title, tag)).or_insert(vec![]);
-            let obj: dbgbb::ArrayObject = $x.clone().try_into().unwrap();
-            entry.push(obj);
+            let mut fields = vec![];
+            (&&dbgbb::Wrap(x)).dbgbb_objects(title, &mut fields);
+            for (title, suffix, obj) in fields {
+                objs.push((($label.to_string(), title, format!("{tag}{suffix}")), obj));
+            }
         )*
+        objs
+    }};
+    (@reduce sum) => { dbgbb::Reduce::Sum };
+    (@reduce mean) => { dbgbb::Reduce::Mean };
+    (@reduce min) => { dbgbb::Reduce::Min };
+    (@reduce max) => { dbgbb::Reduce::Max };
+    (@reduce var) => { dbgbb::Reduce::Var };
+    (@reduce count) => { dbgbb::Reduce::Count };
+    (label => $label:literal, reduce => $op:ident, $($x:expr),*) => {{
+        dbgbb::reduce_acc(dbgbb_acc!(@objs $label, $($x),*), dbgbb_acc!(@reduce $op));
     }};
     (label => $label:literal, every => $n:literal, $($x:expr),*) => {{
-        let mut map = dbgbb::COUNTER.lock().unwrap();
-        let count = map.entry(format!("{}:{}:{}", file!(), line!(), column!())).or_insert(0);
-        if *count % $n == 0 {
+        static SITE: dbgbb::Site = dbgbb::Site::new();
+        if SITE.tick().is_multiple_of($n) {
             dbgbb_acc!(label => $label, $($x),*);
         }
-        *count += 1;
+    }};
+    (label => $label:literal, keep_last => $n:literal, $($x:expr),*) => {{
+        dbgbb::push_acc(dbgbb_acc!(@objs $label, $($x),*), Some($n), None);
+    }};
+    (label => $label:literal, cap => $bytes:expr, $($x:expr),*) => {{
+        dbgbb::push_acc(dbgbb_acc!(@objs $label, $($x),*), None, Some($bytes));
+    }};
+    (label => $label:literal, reservoir => $k:literal, $($x:expr),*) => {{
+        dbgbb::sample_acc(dbgbb_acc!(@objs $label, $($x),*), $k);
+    }};
+    (label => $label:literal, $($x:expr),*) => {{
+        dbgbb::push_acc(dbgbb_acc!(@objs $label, $($x),*), None, None);
     }};
     ($label:literal => post) => {{
-        use dbgbb::{Pack, TryConcat};
-        let mut objs = vec![];
-        let sender = dbgbb::SENDER.lock().unwrap();
-        let mut map = dbgbb::DATA_ACC.lock().unwrap();
-        let keys: Vec<_> = map.keys()
-            .filter(|key| key.0 == $label)
-            .map(|key|key.clone())
-            .collect();
-        for key in keys {
-            let obj = map.remove(&key).unwrap().try_concat().unwrap();
-            objs.push((key.1, key.2, obj));
-        }
-        sender.post(objs).unwrap();
+        dbgbb::post_acc($label);
+    }};
+}
+
+/// Bin the values into a histogram in the process and send it to the server.
+///
+/// Usage:
+/// ```
+/// use dbgbb::dbgbb_hist;
+/// for i in 0..1000 {
+///     let x = (i as f64 * 0.1).sin();
+///     let r = vec![i as f64 + 1.0; 3];
+///     dbgbb_hist!(x, bins => 100, range => (-1.0, 1.0));
+///     dbgbb_hist!(r, bins => 30, range => (1.0, 1e3), log); // Logarithmic bins.
+/// }
+/// dbgbb_hist!(post);
+/// ```
+///
+/// Scalars and arrays of integers or real numbers are binned element-wise. Values outside the range are counted as underflows or overflows, and NaN is ignored. The bin edges, the counts and [underflow, overflow] are sent with `:edges`, `:counts` and `:outliers` appended to the tag by `dbgbb_hist!(post)` or [`post_hist`]. The histograms not sent yet are sent at exit.
+#[macro_export]
+macro_rules! dbgbb_hist {
+    (post) => {{
+        dbgbb::post_hist();
+    }};
+    (@fill $x:expr, $bins:expr, $lo:expr, $hi:expr, $log:literal) => {{
+        use dbgbb::Rename;
+        let x = &$x;
+        let title = match x.get_name() {
+            Some(name) => name,
+            None => stringify!($x).trim_start_matches('&').to_string(),
+        };
+        let tag = format!("{}:{}:{}", file!(), line!(), column!());
+        let obj: dbgbb::ArrayObject = x.clone().try_into().unwrap();
+        dbgbb::fill_hist(title, tag, &obj, $bins, ($lo as f64, $hi as f64), $log);
+    }};
+    ($x:expr, bins => $bins:expr, range => ($lo:expr, $hi:expr), log) => {{
+        dbgbb_hist!(@fill $x, $bins, $lo, $hi, true)
+    }};
+    ($x:expr, bins => $bins:expr, range => ($lo:expr, $hi:expr)) => {{
+        dbgbb_hist!(@fill $x, $bins, $lo, $hi, false)
     }};
 }
 
+/// Measure the elapsed time and accumulate it in seconds under the label.
+///
+/// Usage:
+/// ```
+/// use dbgbb::{dbgbb_acc, dbgbb_time};
+/// for i in 0..10 {
+///     let sum: u64 = dbgbb_time!("assembly", { (0..i).sum() });
+///     dbgbb_time!("solve", reduce => mean, {
+///         std::thread::sleep(std::time::Duration::from_millis(1));
+///     });
+///     let _timer = dbgbb_time!("scope"); // Measured until _timer is dropped.
+/// }
+/// dbgbb_acc!("assembly" => post);
+/// dbgbb_acc!("solve" => post);
+/// dbgbb_acc!("scope" => post);
+/// ```
+///
+/// The durations are accumulated per call site as with `dbgbb_acc!(label => ...)`, where the title is the label. `reduce => op` keeps only the reduced statistics of the durations.
+#[macro_export]
+macro_rules! dbgbb_time {
+    ($label:literal, reduce => $op:ident, $body:block) => {{
+        let _timer = dbgbb::dbgbb_time!($label, reduce => $op);
+        $body
+    }};
+    ($label:literal, $body:block) => {{
+        let _timer = dbgbb::dbgbb_time!($label);
+        $body
+    }};
+    ($label:literal, reduce => $op:ident) => {
+        dbgbb::Timer::start(
+            $label,
+            format!("{}:{}:{}", file!(), line!(), column!()),
+            Some(dbgbb::dbgbb_acc!(@reduce $op)),
+        )
+    };
+    ($label:literal) => {
+        dbgbb::Timer::start($label, format!("{}:{}:{}", file!(), line!(), column!()), None)
+    };
+}
+
 /// Read data from the server.
 ///
 /// Usage:
@@ -144,8 +451,35 @@ macro_rules! dbgbb_acc {
 /// let a: Vec<u64> = dbgbb_read!("a", "src/lib.rs:9:9");
 /// let b: i64 = dbgbb_read!("b", "src/lib.rs:8:9", rev => 0);
 /// ```
+///
+/// A map sent by `dbgbb!(map)` is reassembled from the keys of its last post. The values must be single arrays, not `Option`, `Result` or structs:
+/// ```no_run
+/// use dbgbb::dbgbb_read;
+/// use std::collections::HashMap;
+/// let residuals: HashMap<String, f64> = dbgbb_read!("residuals", map);
+/// ```
+///
+/// Booleans are sent as `u8`, and `mask` reads them back as `Vec<bool>` in the row-major order:
+/// ```no_run
+/// use dbgbb::dbgbb_read;
+/// let active: Vec<bool> = dbgbb_read!("active", mask);
+/// ```
 #[macro_export]
 macro_rules! dbgbb_read {
+    ($title:literal, $tag:literal, map) => {{
+        dbgbb::read_map($title.to_string(), Some($tag.to_string()))
+            .into_iter()
+            .map(|(key, obj)| (key.parse().unwrap(), obj.try_into().unwrap()))
+            .collect()
+    }};
+    ($title:literal, map) => {{
+        dbgbb::read_map($title.to_string(), None)
+            .into_iter()
+            .map(|(key, obj)| (key.parse().unwrap(), obj.try_into().unwrap()))
+            .collect()
+    }};
+    ($title:literal, $tag:literal, mask) => {{ dbgbb::read_mask($title.to_string(), Some($tag.to_string())) }};
+    ($title:literal, mask) => {{ dbgbb::read_mask($title.to_string(), None) }};
     ($title:literal, $tag:literal, rev => $revision:literal) => {{
         let obj = dbgbb::read_bulletin(
             $title.to_string(),
@@ -176,81 +510,35 @@ macro_rules! dbgbb_read {
 /// let a = vec![vec![1u32, 2], vec![3, 4]];
 /// dbgbb_flatten!(a, depth => 1);
 /// dbgbb_flatten!(a, depth => 2);
+/// dbgbb_flatten!(a, depth => all); // Same as depth => 2 here.
 /// ```
+///
+/// Any depth of `Vec`, arrays and slices implementing [`Nested`] is supported. `depth => all` iterates down to the innermost elements.
+///
+/// Arrays of ndarray and nalgebra implementing [`Axes`] are split along the axes instead, e.g. `axis => 0` for the rows and `axes => [0, 1]` for the elements of a matrix.
 #[macro_export]
 macro_rules! dbgbb_flatten {
-    ($x:expr, depth => 1) => {{
-        let sender = dbgbb::SENDER.lock().unwrap();
-        use dbgbb::{Pack, Rename};
-        let title = match $x.get_name() {
-            Some(name) => name,
-            None => stringify!($x).to_string(),
-        };
-        let tag = format!("{}:{}:{}", file!(), line!(), column!());
-        let mut objs = vec![];
-        for inner in $x.clone().iter() {
-            let obj: dbgbb::ArrayObject = inner.clone().try_into().unwrap();
-            objs.push((title.clone(), tag.clone(), obj));
-        }
-        sender.post(objs).unwrap();
-    }};
-    ($x:expr, depth => 2) => {{
-        let sender = dbgbb::SENDER.lock().unwrap();
-        use dbgbb::{Pack, Rename};
-        let title = match $x.get_name() {
-            Some(name) => name,
-            None => stringify!($x).to_string(),
-        };
-        let tag = format!("{}:{}:{}", file!(), line!(), column!());
-        let mut objs = vec![];
-        for inner0 in $x.clone().iter() {
-            for inner1 in inner0.iter() {
-                let obj: dbgbb::ArrayObject = inner1.clone().try_into().unwrap();
-                objs.push((title.clone(), tag.clone(), obj));
-            }
-        }
-        sender.post(objs).unwrap();
-    }};
-    ($x:expr, depth => 3) => {{
-        let sender = dbgbb::SENDER.lock().unwrap();
-        use dbgbb::{Pack, Rename};
-        let title = match $x.get_name() {
-            Some(name) => name,
-            None => stringify!($x).to_string(),
-        };
-        let tag = format!("{}:{}:{}", file!(), line!(), column!());
-        let mut objs = vec![];
-        for inner0 in $x.clone().iter() {
-            for inner1 in inner0.iter() {
-                for inner2 in inner1.iter() {
-                    let obj: dbgbb::ArrayObject = inner2.clone().try_into().unwrap();
-                    objs.push((title.clone(), tag.clone(), obj));
-                }
-            }
-        }
-        sender.post(objs).unwrap();
-    }};
-    ($x:expr, depth => 4) => {{
-        let sender = dbgbb::SENDER.lock().unwrap();
-        use dbgbb::{Pack, Rename};
+    (@post $post:path, $x:expr, $split:expr, $indexed:literal) => {{
+        use dbgbb::Rename;
         let title = match $x.get_name() {
             Some(name) => name,
-            None => stringify!($x).to_string(),
+            None => stringify!($x).trim_start_matches('&').to_string(),
         };
         let tag = format!("{}:{}:{}", file!(), line!(), column!());
-        let mut objs = vec![];
-        for inner0 in $x.clone().iter() {
-            for inner1 in inner0.iter() {
-                for inner2 in inner1.iter() {
-                    for inner3 in inner2.iter() {
-                        let obj: dbgbb::ArrayObject = inner3.clone().try_into().unwrap();
-                        objs.push((title.clone(), tag.clone(), obj));
-                    }
-                }
-            }
-        }
-        sender.post(objs).unwrap();
+        $post(title, tag, &$x, $split, $indexed);
     }};
+    ($x:expr, depth => all) => {
+        dbgbb_flatten!(@post dbgbb::post_nested, $x, None, false)
+    };
+    ($x:expr, depth => $depth:expr) => {
+        dbgbb_flatten!(@post dbgbb::post_nested, $x, Some($depth), false)
+    };
+    ($x:expr, axis => $axis:expr) => {
+        dbgbb_flatten!(@post dbgbb::post_axes, $x, &[$axis], false)
+    };
+    ($x:expr, axes => [$($axis:expr),*]) => {
+        dbgbb_flatten!(@post dbgbb::post_axes, $x, &[$($axis),*], false)
+    };
 }
 
 /// Create a single array and send it to the server. The lengths of the elements should be the same.
@@ -260,102 +548,20 @@ macro_rules! dbgbb_flatten {
 /// use dbgbb::dbgbb_concat;
 /// let a = vec![vec![1u32, 2], vec![3, 4]];
 /// dbgbb_concat!(a, depth => 1);
+/// dbgbb_concat!(a, depth => all);
 /// ```
+///
+/// The result does not depend on the depth, as the elements are concatenated back into an array of the original nesting.
 #[macro_export]
 macro_rules! dbgbb_concat {
-    ($x:expr, depth => 1) => {{
-        let sender = dbgbb::SENDER.lock().unwrap();
-        use dbgbb::{Pack, Rename, TryConcat};
-        let title = match $x.get_name() {
-            Some(name) => name,
-            None => stringify!($x).to_string(),
-        };
-        let tag = format!("{}:{}:{}", file!(), line!(), column!());
-        let mut objs = vec![];
-        for inner in $x.clone().iter() {
-            let obj: dbgbb::ArrayObject = inner.clone().try_into().unwrap();
-            objs.push(obj);
-        }
-        let cat = objs.try_concat().unwrap();
-        sender.post(vec![(title, tag, cat)]).unwrap();
-    }};
-    ($x:expr, depth => 2) => {{
-        let sender = dbgbb::SENDER.lock().unwrap();
-        use dbgbb::{Pack, Rename, TryConcat};
+    ($x:expr, depth => $depth:tt) => {{
+        use dbgbb::{Nested, Rename};
         let title = match $x.get_name() {
             Some(name) => name,
-            None => stringify!($x).to_string(),
+            None => stringify!($x).trim_start_matches('&').to_string(),
         };
         let tag = format!("{}:{}:{}", file!(), line!(), column!());
-        let mut objs0 = vec![];
-        for inner0 in $x.clone().iter() {
-            let mut objs1 = vec![];
-            for inner1 in inner0.iter() {
-                let obj: dbgbb::ArrayObject = inner1.clone().try_into().unwrap();
-                objs1.push(obj);
-            }
-            let cat = objs1.try_concat().unwrap();
-            objs0.push(cat);
-        }
-        let cat = objs0.try_concat().unwrap();
-        sender.post(vec![(title, tag, cat)]).unwrap();
-    }};
-    ($x:expr, depth => 3) => {{
-        let sender = dbgbb::SENDER.lock().unwrap();
-        use dbgbb::{Pack, Rename, TryConcat};
-        let title = match $x.get_name() {
-            Some(name) => name,
-            None => stringify!($x).to_string(),
-        };
-        let tag = format!("{}:{}:{}", file!(), line!(), column!());
-        let mut objs0 = vec![];
-        for inner0 in $x.clone().iter() {
-            let mut objs1 = vec![];
-            for inner1 in inner0.iter() {
-                let mut objs2 = vec![];
-                for inner2 in inner1.iter() {
-                    let obj: dbgbb::ArrayObject = inner2.clone().try_into().unwrap();
-                    objs2.push(obj);
-                }
-                let cat = objs2.try_concat().unwrap();
-                objs1.push(cat);
-            }
-            let cat = objs1.try_concat().unwrap();
-            objs0.push(cat);
-        }
-        let cat = objs0.try_concat().unwrap();
-        sender.post(vec![(title, tag, cat)]).unwrap();
-    }};
-    ($x:expr, depth => 4) => {{
-        let sender = dbgbb::SENDER.lock().unwrap();
-        use dbgbb::{Pack, Rename, TryConcat};
-        let title = match $x.get_name() {
-            Some(name) => name,
-            None => stringify!($x).to_string(),
-        };
-        let tag = format!("{}:{}:{}", file!(), line!(), column!());
-        let mut objs0 = vec![];
-        for inner0 in $x.clone().iter() {
-            let mut objs1 = vec![];
-            for inner1 in inner0.iter() {
-                let mut objs2 = vec![];
-                for inner2 in inner1.iter() {
-                    let mut objs3 = vec![];
-                    for inner3 in inner2.iter() {
-                        let obj: dbgbb::ArrayObject = inner3.clone().try_into().unwrap();
-                        objs3.push(obj);
-                    }
-                    let cat = objs3.try_concat().unwrap();
-                    objs2.push(cat);
-                }
-                let cat = objs2.try_concat().unwrap();
-                objs1.push(cat);
-            }
-            let cat = objs1.try_concat().unwrap();
-            objs0.push(cat);
-        }
-        let cat = objs0.try_concat().unwrap();
-        sender.post(vec![(title, tag, cat)]).unwrap();
+        dbgbb::post(vec![(title, tag, $x.to_object())]).unwrap();
     }};
 }
 
@@ -367,79 +573,31 @@ macro_rules! dbgbb_concat {
 /// let a = vec![vec![1u32, 2], vec![3, 4]];
 /// dbgbb_index!(a, depth => 1);
 /// dbgbb_index!(a, depth => 2);
+/// dbgbb_index!(a, depth => all);
 /// ```
+///
+/// For ndarray and nalgebra, `dbgbb_index!(x, axis => 1)` sends the columns of a matrix with `:[j]` appended to the tag, and `dbgbb_index!(x, axes => [0, 2])` sends the lanes along the axis 1 of a 3-dimensional array with `:[i,k]`.
 #[macro_export]
 macro_rules! dbgbb_index {
-    ($x:expr, depth => 1) => {{
-        let sender = dbgbb::SENDER.lock().unwrap();
-        use dbgbb::{Pack, Rename};
-        let title = match $x.get_name() {
-            Some(name) => name,
-            None => stringify!($x).to_string(),
-        };
-        let tag = format!("{}:{}:{}", file!(), line!(), column!());
-        let mut objs = vec![];
-        for (i, inner) in $x.clone().iter().enumerate() {
-            let obj: dbgbb::ArrayObject = inner.clone().try_into().unwrap();
-            objs.push((title.clone(), format!("{tag}:[{i}]"), obj));
-        }
-        sender.post(objs).unwrap();
-    }};
-    ($x:expr, depth => 2) => {{
-        let sender = dbgbb::SENDER.lock().unwrap();
-        use dbgbb::{Pack, Rename};
-        let title = match $x.get_name() {
-            Some(name) => name,
-            None => stringify!($x).to_string(),
-        };
-        let tag = format!("{}:{}:{}", file!(), line!(), column!());
-        let mut objs = vec![];
-        for (i, inner0) in $x.clone().iter().enumerate() {
-            for (j, inner1) in inner0.iter().enumerate() {
-                let obj: dbgbb::ArrayObject = inner1.clone().try_into().unwrap();
-                objs.push((title.clone(), format!("{tag}:[{i},{j}]"), obj));
-            }
-        }
-        sender.post(objs).unwrap();
-    }};
-    ($x:expr, depth => 3) => {{
-        let sender = dbgbb::SENDER.lock().unwrap();
-        use dbgbb::{Pack, Rename};
-        let title = match $x.get_name() {
-            Some(name) => name,
-            None => stringify!($x).to_string(),
-        };
-        let tag = format!("{}:{}:{}", file!(), line!(), column!());
-        let mut objs = vec![];
-        for (i, inner0) in $x.clone().iter().enumerate() {
-            for (j, inner1) in inner0.iter().enumerate() {
-                for (k, inner2) in inner1.iter().enumerate() {
-                    let obj: dbgbb::ArrayObject = inner2.clone().try_into().unwrap();
-                    objs.push((title.clone(), format!("{tag}:[{i},{j},{k}]"), obj));
-                }
-            }
-        }
-        sender.post(objs).unwrap();
-    }};
-    ($x:expr, depth => 4) => {{
-        let sender = dbgbb::SENDER.lock().unwrap();
-        use dbgbb::{Pack, Rename};
+    (@post $post:path, $x:expr, $split:expr) => {{
+        use dbgbb::Rename;
         let title = match $x.get_name() {
             Some(name) => name,
-            None => stringify!($x).to_string(),
+            None => stringify!($x).trim_start_matches('&').to_string(),
         };
         let tag = format!("{}:{}:{}", file!(), line!(), column!());
-        let mut objs = vec![];
-        for (i, inner0) in $x.clone().iter().enumerate() {
-            for (j, inner1) in inner0.iter().enumerate() {
-                for (k, inner2) in inner1.iter().enumerate() {
-                    for (l, inner3) in inner2.iter().enumerate() {
-                        let obj: dbgbb::ArrayObject = inner3.clone().try_into().unwrap();
-                        objs.push((title.clone(), format!("{tag}:[{i},{j},{k},{l}]"), obj));
-                    }
-                }
-            }
-        }
-        sender.post(objs).unwrap();
+        $post(title, tag, &$x, $split, true);
     }};
+    ($x:expr, depth => all) => {
+        dbgbb_index!(@post dbgbb::post_nested, $x, None)
+    };
+    ($x:expr, depth => $depth:expr) => {
+        dbgbb_index!(@post dbgbb::post_nested, $x, Some($depth))
+    };
+    ($x:expr, axis => $axis:expr) => {
+        dbgbb_index!(@post dbgbb::post_axes, $x, &[$axis])
+    };
+    ($x:expr, axes => [$($axis:expr),*]) => {
+        dbgbb_index!(@post dbgbb::post_axes, $x, &[$($axis),*])
+    };
 }
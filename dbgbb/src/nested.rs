@@ -0,0 +1,313 @@
+#[cfg(any(feature = "ndarray_15", feature = "ndarray_16", feature = "nalgebra"))]
+use crate::objects::Element;
+use crate::rename::Renamed;
+use array_object::{ArrayObject, TryConcat};
+use num_complex::Complex;
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Nested data of arbitrary depth for `dbgbb_flatten!`, `dbgbb_concat!` and `dbgbb_index!`.
+pub trait Nested {
+    /// Converts the whole data into a single array. Panics if the lengths of the elements differ.
+    fn to_object(&self) -> ArrayObject;
+    /// Calls `f` with the indices and the elements `depth` levels down, or the innermost elements if `depth` is None.
+    fn visit(
+        &self,
+        depth: Option<usize>,
+        index: &mut Vec<usize>,
+        f: &mut dyn FnMut(&[usize], ArrayObject),
+    ) {
+        let _ = depth;
+        f(index, self.to_object());
+    }
+    /// Converts a sequence of the data into a single array.
+    #[doc(hidden)]
+    fn slice_to_object(items: &[Self]) -> ArrayObject
+    where
+        Self: Sized,
+    {
+        items
+            .iter()
+            .map(|item| item.to_object())
+            .collect::<Vec<_>>()
+            .try_concat()
+            .unwrap()
+    }
+}
+
+macro_rules! impl_nested {
+    ($($ty:ty),*) => {
+        $(
+            impl Nested for $ty {
+                fn to_object(&self) -> ArrayObject {
+                    self.clone().into()
+                }
+                fn slice_to_object(items: &[Self]) -> ArrayObject {
+                    items.to_vec().into()
+                }
+            }
+        )*
+    }
+}
+
+impl_nested!(
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    Complex<f32>,
+    Complex<f64>,
+    String,
+    &str
+);
+
+impl Nested for bool {
+    fn to_object(&self) -> ArrayObject {
+        u8::from(*self).into()
+    }
+    fn slice_to_object(items: &[Self]) -> ArrayObject {
+        items
+            .iter()
+            .map(|&b| u8::from(b))
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+impl<T: Nested> Nested for [T] {
+    fn to_object(&self) -> ArrayObject {
+        T::slice_to_object(self)
+    }
+    fn visit(
+        &self,
+        depth: Option<usize>,
+        index: &mut Vec<usize>,
+        f: &mut dyn FnMut(&[usize], ArrayObject),
+    ) {
+        if depth == Some(0) {
+            f(index, self.to_object());
+            return;
+        }
+        for (i, item) in self.iter().enumerate() {
+            index.push(i);
+            item.visit(depth.map(|depth| depth - 1), index, f);
+            index.pop();
+        }
+    }
+}
+
+impl<T: Nested> Nested for Vec<T> {
+    fn to_object(&self) -> ArrayObject {
+        self.as_slice().to_object()
+    }
+    fn visit(
+        &self,
+        depth: Option<usize>,
+        index: &mut Vec<usize>,
+        f: &mut dyn FnMut(&[usize], ArrayObject),
+    ) {
+        self.as_slice().visit(depth, index, f);
+    }
+}
+
+impl<T: Nested, const N: usize> Nested for [T; N] {
+    fn to_object(&self) -> ArrayObject {
+        self.as_slice().to_object()
+    }
+    fn visit(
+        &self,
+        depth: Option<usize>,
+        index: &mut Vec<usize>,
+        f: &mut dyn FnMut(&[usize], ArrayObject),
+    ) {
+        self.as_slice().visit(depth, index, f);
+    }
+}
+
+impl<T: Nested + Clone> Nested for VecDeque<T> {
+    fn to_object(&self) -> ArrayObject {
+        match self.as_slices() {
+            (front, []) => front.to_object(),
+            _ => self.iter().cloned().collect::<Vec<_>>().to_object(),
+        }
+    }
+    fn visit(
+        &self,
+        depth: Option<usize>,
+        index: &mut Vec<usize>,
+        f: &mut dyn FnMut(&[usize], ArrayObject),
+    ) {
+        if depth == Some(0) {
+            f(index, self.to_object());
+            return;
+        }
+        for (i, item) in self.iter().enumerate() {
+            index.push(i);
+            item.visit(depth.map(|depth| depth - 1), index, f);
+            index.pop();
+        }
+    }
+}
+
+impl<T: Nested + ?Sized> Nested for &T {
+    fn to_object(&self) -> ArrayObject {
+        (**self).to_object()
+    }
+    fn visit(
+        &self,
+        depth: Option<usize>,
+        index: &mut Vec<usize>,
+        f: &mut dyn FnMut(&[usize], ArrayObject),
+    ) {
+        (**self).visit(depth, index, f);
+    }
+}
+
+impl_pointer!(Nested {
+    fn to_object(&self) -> ArrayObject {
+        (**self).to_object()
+    }
+    fn visit(
+        &self,
+        depth: Option<usize>,
+        index: &mut Vec<usize>,
+        f: &mut dyn FnMut(&[usize], ArrayObject),
+    ) {
+        (**self).visit(depth, index, f);
+    }
+});
+
+impl<T: Nested> Nested for Renamed<T> {
+    fn to_object(&self) -> ArrayObject {
+        self.data.to_object()
+    }
+    fn visit(
+        &self,
+        depth: Option<usize>,
+        index: &mut Vec<usize>,
+        f: &mut dyn FnMut(&[usize], ArrayObject),
+    ) {
+        self.data.visit(depth, index, f);
+    }
+}
+
+/// Arrays split along the chosen axes for `dbgbb_flatten!(x, axes => [...])` and `dbgbb_index!(x, axis => ...)`.
+pub trait Axes {
+    /// Calls `f` with the indices along the axes and the sub-array with the axes removed.
+    fn visit_axes(&self, axes: &[usize], f: &mut dyn FnMut(&[usize], ArrayObject));
+}
+
+impl<T: Axes + ?Sized> Axes for &T {
+    fn visit_axes(&self, axes: &[usize], f: &mut dyn FnMut(&[usize], ArrayObject)) {
+        (**self).visit_axes(axes, f);
+    }
+}
+
+impl<T: Axes> Axes for Renamed<T> {
+    fn visit_axes(&self, axes: &[usize], f: &mut dyn FnMut(&[usize], ArrayObject)) {
+        self.data.visit_axes(axes, f);
+    }
+}
+
+#[cfg(any(feature = "ndarray_15", feature = "ndarray_16", feature = "nalgebra"))]
+/// Splits the data in the row-major order along the axes. The first axis is the outermost.
+pub(crate) fn split_axes<T: Element>(
+    data: &[T],
+    shape: &[usize],
+    axes: &[usize],
+    f: &mut dyn FnMut(&[usize], ArrayObject),
+) {
+    for (i, &axis) in axes.iter().enumerate() {
+        assert!(axis < shape.len(), "The axis {axis} is out of range.");
+        assert!(!axes[..i].contains(&axis), "The axis {axis} is repeated.");
+    }
+    let mut strides = vec![1; shape.len()];
+    for i in (1..shape.len()).rev() {
+        strides[i - 1] = strides[i] * shape[i];
+    }
+    let rest: Vec<_> = (0..shape.len()).filter(|i| !axes.contains(i)).collect();
+    let outer: Vec<_> = axes.iter().map(|&i| shape[i]).collect();
+    let inner: Vec<_> = rest.iter().map(|&i| shape[i]).collect();
+    let offset = |axes: &[usize], index: &[usize]| -> usize {
+        axes.iter().zip(index).map(|(&a, &i)| i * strides[a]).sum()
+    };
+    for index in indices(&outer) {
+        let base = offset(axes, &index);
+        let slice: Vec<_> = indices(&inner)
+            .map(|j| data[base + offset(&rest, &j)].clone())
+            .collect();
+        let shape = inner.iter().map(|&n| n as u64).collect();
+        f(&index, T::to_object(slice, shape));
+    }
+}
+
+#[cfg(any(feature = "ndarray_15", feature = "ndarray_16", feature = "nalgebra"))]
+/// Iterates over the indices of the shape in the row-major order.
+fn indices(shape: &[usize]) -> impl Iterator<Item = Vec<usize>> + '_ {
+    let total: usize = shape.iter().product();
+    (0..total).map(move |mut k| {
+        let mut index = vec![0; shape.len()];
+        for (i, &n) in shape.iter().enumerate().rev() {
+            index[i] = k % n;
+            k /= n;
+        }
+        index
+    })
+}
+
+fn index_tag(tag: &str, index: &[usize]) -> String {
+    let index: Vec<_> = index.iter().map(|i| i.to_string()).collect();
+    format!("{tag}:[{}]", index.join(","))
+}
+
+/// [Only for internal use] Helper function for `dbgbb_flatten!(...)` and `dbgbb_index!(...)`. The indices are appended to the tag if `indexed`.
+pub fn post_nested<T: Nested + ?Sized>(
+    title: String,
+    tag: String,
+    data: &T,
+    depth: Option<usize>,
+    indexed: bool,
+) {
+    let mut objs = vec![];
+    data.visit(depth, &mut vec![], &mut |index, obj| {
+        let tag = if indexed {
+            index_tag(&tag, index)
+        } else {
+            tag.clone()
+        };
+        objs.push((title.clone(), tag, obj));
+    });
+    crate::post(objs).unwrap();
+}
+
+/// [Only for internal use] Helper function for `dbgbb_flatten!(x, axes => [...])` and `dbgbb_index!(x, axis => ...)`.
+pub fn post_axes<T: Axes + ?Sized>(
+    title: String,
+    tag: String,
+    data: &T,
+    axes: &[usize],
+    indexed: bool,
+) {
+    let mut objs = vec![];
+    data.visit_axes(axes, &mut |index, obj| {
+        let tag = if indexed {
+            index_tag(&tag, index)
+        } else {
+            tag.clone()
+        };
+        objs.push((title.clone(), tag, obj));
+    });
+    crate::post(objs).unwrap();
+}
@@ -0,0 +1,436 @@
+use crate::accumulator::is_uniform;
+use crate::rename::Renamed;
+use array_object::adaptor::VecShape;
+use array_object::{ArrayObject, TryConcat};
+use num_complex::Complex;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::{Debug, Display};
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Data sent as one or more arrays, e.g. a struct with `#[derive(DbgBB)]`. A sequence of such data is sent as one array per title.
+pub trait DbgBB {
+    /// Appends the arrays with their titles and the suffixes of the tag, which are usually empty.
+    fn objects(&self, title: &str, objs: &mut Vec<(String, String, ArrayObject)>);
+    /// Appends the arrays of a sequence, where the arrays of the same title are concatenated into an array of one higher dimension.
+    #[doc(hidden)]
+    fn slice_objects(items: &[Self], title: &str, objs: &mut Vec<(String, String, ArrayObject)>)
+    where
+        Self: Sized,
+    {
+        concat_columns(items, title, objs);
+    }
+    /// Returns the value if it is a number, so that a sequence of tuples of numbers is written into a flat buffer.
+    #[doc(hidden)]
+    fn scalar(&self) -> Option<Scalar> {
+        None
+    }
+}
+
+/// Concatenates the arrays of the same title over the items. The arrays of different shapes are sent with the index `:[i]` appended to the tag.
+fn concat_columns<T: DbgBB>(
+    items: &[T],
+    title: &str,
+    objs: &mut Vec<(String, String, ArrayObject)>,
+) {
+    let mut columns: Vec<(String, String, Vec<ArrayObject>)> = vec![];
+    for item in items {
+        let mut fields = vec![];
+        item.objects(title, &mut fields);
+        for (name, suffix, obj) in fields {
+            match columns
+                .iter_mut()
+                .find(|(title, tag, _)| *title == name && *tag == suffix)
+            {
+                Some((_, _, column)) => column.push(obj),
+                None => columns.push((name, suffix, vec![obj])),
+            }
+        }
+    }
+    for (name, suffix, column) in columns {
+        if is_uniform(&column) {
+            objs.push((name, suffix, column.try_concat().unwrap()));
+        } else {
+            // Ragged data is sent as an indexed series as in `dbgbb_acc!`.
+            for (i, obj) in column.into_iter().enumerate() {
+                objs.push((name.clone(), format!("{suffix}:[{i}]"), obj));
+            }
+        }
+    }
+}
+
+macro_rules! impl_dbgbb {
+    ($($variant:ident $ty:ty),*) => {
+        /// [Only for internal use] Number in a tuple.
+        #[doc(hidden)]
+        pub enum Scalar {
+            $($variant($ty)),*
+        }
+
+        /// Flat buffer of the numbers of the same type.
+        enum Scalars {
+            $($variant(Vec<$ty>)),*
+        }
+
+        impl Scalars {
+            fn new(first: Scalar, capacity: usize) -> Self {
+                match first {
+                    $(Scalar::$variant(x) => {
+                        let mut buffer = Vec::with_capacity(capacity);
+                        buffer.push(x);
+                        Scalars::$variant(buffer)
+                    })*
+                }
+            }
+            /// Returns false if the type differs from that of the buffer.
+            fn push(&mut self, x: Scalar) -> bool {
+                match (self, x) {
+                    $((Scalars::$variant(buffer), Scalar::$variant(x)) => buffer.push(x),)*
+                    _ => return false,
+                }
+                true
+            }
+            fn into_object(self, shape: Vec<u64>) -> ArrayObject {
+                match self {
+                    $(Scalars::$variant(buffer) => VecShape(buffer, shape).try_into().unwrap()),*
+                }
+            }
+        }
+
+        $(
+            impl DbgBB for $ty {
+                fn objects(&self, title: &str, objs: &mut Vec<(String, String, ArrayObject)>) {
+                    objs.push((title.to_string(), String::new(), self.clone().into()));
+                }
+                fn slice_objects(items: &[Self], title: &str, objs: &mut Vec<(String, String, ArrayObject)>) {
+                    objs.push((title.to_string(), String::new(), items.to_vec().into()));
+                }
+                fn scalar(&self) -> Option<Scalar> {
+                    Some(Scalar::$variant(self.clone()))
+                }
+            }
+        )*
+    };
+    (@text $($ty:ty),*) => {
+        $(
+            impl DbgBB for $ty {
+                fn objects(&self, title: &str, objs: &mut Vec<(String, String, ArrayObject)>) {
+                    objs.push((title.to_string(), String::new(), self.clone().into()));
+                }
+                fn slice_objects(items: &[Self], title: &str, objs: &mut Vec<(String, String, ArrayObject)>) {
+                    objs.push((title.to_string(), String::new(), items.to_vec().into()));
+                }
+            }
+        )*
+    };
+}
+
+impl_dbgbb!(
+    U8 u8,
+    U16 u16,
+    U32 u32,
+    U64 u64,
+    U128 u128,
+    Usize usize,
+    I8 i8,
+    I16 i16,
+    I32 i32,
+    I64 i64,
+    I128 i128,
+    Isize isize,
+    F32 f32,
+    F64 f64,
+    C32 Complex<f32>,
+    C64 Complex<f64>
+);
+
+impl_dbgbb!(@text String, &str);
+
+/// Writes the rows of numbers of the same type into an array of the shape `[n, k]`. Returns None otherwise.
+fn scalar_rows(
+    mut scalars: impl Iterator<Item = Option<Scalar>>,
+    n: usize,
+    k: usize,
+) -> Option<ArrayObject> {
+    let mut buffer = Scalars::new(scalars.next()??, n * k);
+    for x in scalars {
+        if !buffer.push(x?) {
+            return None;
+        }
+    }
+    Some(buffer.into_object(vec![n as u64, k as u64]))
+}
+
+/// Booleans are sent as `u8`, i.e. 0 or 1.
+impl DbgBB for bool {
+    fn objects(&self, title: &str, objs: &mut Vec<(String, String, ArrayObject)>) {
+        objs.push((title.to_string(), String::new(), u8::from(*self).into()));
+    }
+    fn slice_objects(items: &[Self], title: &str, objs: &mut Vec<(String, String, ArrayObject)>) {
+        let bytes: Vec<_> = items.iter().map(|&b| u8::from(b)).collect();
+        objs.push((title.to_string(), String::new(), bytes.into()));
+    }
+}
+
+/// [Only for internal use] Elements of the arrays of ndarray and nalgebra.
+#[doc(hidden)]
+pub trait Element: Clone {
+    /// Converts the data in the row-major order into an array of the shape.
+    fn to_object(data: Vec<Self>, shape: Vec<u64>) -> ArrayObject;
+}
+
+macro_rules! impl_element {
+    ($($ty:ty),*) => {
+        $(
+            impl Element for $ty {
+                fn to_object(data: Vec<Self>, shape: Vec<u64>) -> ArrayObject {
+                    VecShape(data, shape).try_into().unwrap()
+                }
+            }
+        )*
+    }
+}
+
+impl_element!(
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    Complex<f32>,
+    Complex<f64>,
+    String,
+    &str
+);
+
+impl Element for bool {
+    fn to_object(data: Vec<Self>, shape: Vec<u64>) -> ArrayObject {
+        let bytes: Vec<_> = data.into_iter().map(u8::from).collect();
+        VecShape(bytes, shape).try_into().unwrap()
+    }
+}
+
+macro_rules! impl_tuple {
+    ($($name:ident $index:tt),+) => {
+        impl<$($name: DbgBB),+> DbgBB for ($($name,)+) {
+            fn objects(&self, title: &str, objs: &mut Vec<(String, String, ArrayObject)>) {
+                $(
+                    self.$index.objects(&format!("{title}.{}", $index), objs);
+                )+
+            }
+            /// Tuples of the arrays of the same type and shape are sent as an array of one higher dimension, e.g. `[n, 2]` for `Vec<(f64, f64)>`.
+            fn slice_objects(items: &[Self], title: &str, objs: &mut Vec<(String, String, ArrayObject)>) {
+                if items.is_empty() {
+                    return;
+                }
+                let arity = [$($index),+].len();
+                let scalars = items.iter().flat_map(|item| [$(item.$index.scalar()),+]);
+                if let Some(obj) = scalar_rows(scalars, items.len(), arity) {
+                    objs.push((title.to_string(), String::new(), obj));
+                    return;
+                }
+                let rows: Option<Vec<_>> = items
+                    .iter()
+                    .map(|item| {
+                        let mut fields = vec![];
+                        item.objects(title, &mut fields);
+                        if fields.len() != arity {
+                            return None;
+                        }
+                        let row: Vec<_> = fields.into_iter().map(|(_, _, obj)| obj).collect();
+                        row.try_concat().ok()
+                    })
+                    .collect();
+                match rows.and_then(|rows| rows.try_concat().ok()) {
+                    Some(obj) => objs.push((title.to_string(), String::new(), obj)),
+                    None => concat_columns(items, title, objs),
+                }
+            }
+        }
+    }
+}
+
+impl_tuple!(A 0);
+impl_tuple!(A 0, B 1);
+impl_tuple!(A 0, B 1, C 2);
+impl_tuple!(A 0, B 1, C 2, D 3);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+impl<T: DbgBB> DbgBB for [T] {
+    fn objects(&self, title: &str, objs: &mut Vec<(String, String, ArrayObject)>) {
+        T::slice_objects(self, title, objs);
+    }
+}
+
+impl<T: DbgBB> DbgBB for Vec<T> {
+    fn objects(&self, title: &str, objs: &mut Vec<(String, String, ArrayObject)>) {
+        T::slice_objects(self, title, objs);
+    }
+}
+
+impl<T: DbgBB, const N: usize> DbgBB for [T; N] {
+    fn objects(&self, title: &str, objs: &mut Vec<(String, String, ArrayObject)>) {
+        T::slice_objects(self, title, objs);
+    }
+}
+
+impl<T: DbgBB + Clone> DbgBB for VecDeque<T> {
+    fn objects(&self, title: &str, objs: &mut Vec<(String, String, ArrayObject)>) {
+        match self.as_slices() {
+            (front, []) => T::slice_objects(front, title, objs),
+            _ => T::slice_objects(&self.iter().cloned().collect::<Vec<_>>(), title, objs),
+        }
+    }
+}
+
+/// The key is appended to the tag as `:[key]`, and the list of the keys is sent with `:keys`.
+impl<K: Display, V: DbgBB, S> DbgBB for HashMap<K, V, S> {
+    fn objects(&self, title: &str, objs: &mut Vec<(String, String, ArrayObject)>) {
+        map_objects(self.iter(), title, objs);
+    }
+}
+
+/// The key is appended to the tag as `:[key]`, and the list of the keys is sent with `:keys`.
+impl<K: Display, V: DbgBB> DbgBB for BTreeMap<K, V> {
+    fn objects(&self, title: &str, objs: &mut Vec<(String, String, ArrayObject)>) {
+        map_objects(self.iter(), title, objs);
+    }
+}
+
+fn map_objects<'a, K: Display + 'a, V: DbgBB + 'a>(
+    entries: impl Iterator<Item = (&'a K, &'a V)>,
+    title: &str,
+    objs: &mut Vec<(String, String, ArrayObject)>,
+) {
+    let mut keys = vec![];
+    for (key, value) in entries {
+        let mut fields = vec![];
+        value.objects(title, &mut fields);
+        for (name, suffix, obj) in fields {
+            objs.push((name, format!(":[{key}]{suffix}"), obj));
+        }
+        keys.push(key.to_string());
+    }
+    // The current keys, with which the keys removed since the last post are not read back.
+    objs.push((title.to_string(), ":keys".to_string(), keys.into()));
+}
+
+/// `None` is sent as the string `"None"` with `:none` appended to the tag.
+impl<T: DbgBB> DbgBB for Option<T> {
+    fn objects(&self, title: &str, objs: &mut Vec<(String, String, ArrayObject)>) {
+        match self {
+            Some(value) => value.objects(title, objs),
+            None => objs.push((title.to_string(), ":none".to_string(), "None".into())),
+        }
+    }
+}
+
+/// `Err` is sent as the debug string of the error with `:err` appended to the tag.
+impl<T: DbgBB, E: Debug> DbgBB for Result<T, E> {
+    fn objects(&self, title: &str, objs: &mut Vec<(String, String, ArrayObject)>) {
+        match self {
+            Ok(value) => value.objects(title, objs),
+            Err(err) => objs.push((
+                title.to_string(),
+                ":err".to_string(),
+                format!("{err:?}").into(),
+            )),
+        }
+    }
+}
+
+impl<T: DbgBB + ?Sized> DbgBB for &T {
+    fn objects(&self, title: &str, objs: &mut Vec<(String, String, ArrayObject)>) {
+        (**self).objects(title, objs);
+    }
+    fn scalar(&self) -> Option<Scalar> {
+        (**self).scalar()
+    }
+}
+
+impl_pointer!(DbgBB {
+    fn objects(&self, title: &str, objs: &mut Vec<(String, String, ArrayObject)>) {
+        (**self).objects(title, objs);
+    }
+});
+
+impl<T: DbgBB> DbgBB for Renamed<T> {
+    fn objects(&self, title: &str, objs: &mut Vec<(String, String, ArrayObject)>) {
+        self.data.objects(title, objs);
+    }
+}
+
+/// [Only for internal use] Helper struct for the macros.
+///
+/// The conversion is chosen by the method resolution of `(&&Wrap(&x)).dbgbb_objects(...)`, where [`ViaDbgBB`] takes precedence over [`ViaRef`], and [`ViaRef`] over [`ViaTryInto`].
+#[doc(hidden)]
+pub struct Wrap<'a, T: ?Sized>(pub &'a T);
+
+/// [Only for internal use] Conversion of the data implementing [`DbgBB`].
+#[doc(hidden)]
+pub trait ViaDbgBB {
+    fn dbgbb_objects(&self, title: String, objs: &mut Vec<(String, String, ArrayObject)>);
+}
+
+impl<T: DbgBB + ?Sized> ViaDbgBB for &Wrap<'_, T> {
+    fn dbgbb_objects(&self, title: String, objs: &mut Vec<(String, String, ArrayObject)>) {
+        self.0.objects(&title, objs);
+    }
+}
+
+/// [Only for internal use] Conversion of a reference to the data into a single array.
+#[doc(hidden)]
+pub trait ViaRef {
+    fn dbgbb_objects(&self, title: String, objs: &mut Vec<(String, String, ArrayObject)>);
+}
+
+impl<T: Clone + TryInto<ArrayObject, Error: std::fmt::Debug>> ViaRef for &&Wrap<'_, &T> {
+    fn dbgbb_objects(&self, title: String, objs: &mut Vec<(String, String, ArrayObject)>) {
+        objs.push((title, String::new(), (*self.0).clone().try_into().unwrap()));
+    }
+}
+
+impl<T: Clone + TryInto<ArrayObject, Error: std::fmt::Debug>> ViaRef for &&Wrap<'_, &Renamed<T>> {
+    fn dbgbb_objects(&self, title: String, objs: &mut Vec<(String, String, ArrayObject)>) {
+        objs.push((
+            title,
+            String::new(),
+            self.0.data.clone().try_into().unwrap(),
+        ));
+    }
+}
+
+/// [Only for internal use] Conversion of the data into a single array.
+#[doc(hidden)]
+pub trait ViaTryInto {
+    fn dbgbb_objects(&self, title: String, objs: &mut Vec<(String, String, ArrayObject)>);
+}
+
+impl<T: Clone + TryInto<ArrayObject, Error: std::fmt::Debug>> ViaTryInto for Wrap<'_, T> {
+    fn dbgbb_objects(&self, title: String, objs: &mut Vec<(String, String, ArrayObject)>) {
+        objs.push((title, String::new(), self.0.clone().try_into().unwrap()));
+    }
+}
+
+impl<T: Clone + TryInto<ArrayObject, Error: std::fmt::Debug>> ViaTryInto for Wrap<'_, Renamed<T>> {
+    fn dbgbb_objects(&self, title: String, objs: &mut Vec<(String, String, ArrayObject)>) {
+        objs.push((
+            title,
+            String::new(),
+            self.0.data.clone().try_into().unwrap(),
+        ));
+    }
+}
@@ -3,7 +3,8 @@ pub use std::net::TcpStream as TcpOrUnixStream;
 #[cfg(feature = "unix")]
 pub use std::os::unix::net::UnixStream as TcpOrUnixStream;
 
-use crate::{Operation, Response, SENDER};
+use crate::{Operation, Response, SENDER, lock};
+use array_object::adaptor::VecShape;
 use array_object::{ArrayObject, Unpack};
 use serde_bytes::ByteBuf;
 
@@ -13,9 +14,9 @@ pub fn read_bulletin(title: String, tag: Option<String>, revision: Option<u64>)
         Some(rev) => vec![rev],
         None => vec![],
     };
-    let sender = SENDER.lock().unwrap();
+    let sender = lock(&SENDER);
     let addr = sender.get_addr();
-    let mut stream = TcpOrUnixStream::connect(&addr).unwrap();
+    let mut stream = TcpOrUnixStream::connect(addr).unwrap();
     let mut buffer = std::io::Cursor::new(vec![]);
     ciborium::into_writer(&Operation::Read, &mut buffer).unwrap();
     ciborium::into_writer(&(title, tag, revisions), &mut buffer).unwrap();
@@ -38,3 +39,51 @@ pub fn read_bulletin(title: String, tag: Option<String>, revision: Option<u64>)
         }
     }
 }
+
+/// [Only for internal use] Helper function for `dbgbb_read!(..., mask)`. The booleans are returned in the row-major order.
+pub fn read_mask(title: String, tag: Option<String>) -> Vec<bool> {
+    let VecShape::<u8>(bytes, _) = read_bulletin(title, tag, None).try_into().unwrap();
+    bytes.into_iter().map(|b| b != 0).collect()
+}
+
+/// [Only for internal use] Helper function for `dbgbb_read!(..., map)`. Returns the keys and the values of the last map posted by `dbgbb!(map)`.
+pub fn read_map(title: String, tag: Option<String>) -> Vec<(String, ArrayObject)> {
+    let tags: Vec<_> = view_board()
+        .into_iter()
+        .filter(|(entry, _, _)| *entry == title)
+        .map(|(_, tag, _)| tag)
+        .collect();
+    let bases: Vec<_> = tags
+        .iter()
+        .filter_map(|full_tag| full_tag.strip_suffix(":keys"))
+        .filter(|base| tag.as_ref().is_none_or(|tag| tag == base))
+        .collect();
+    let base = match bases[..] {
+        [] => panic!("Not found."),
+        [base] => base,
+        _ => panic!("Multiple entries found: {:?}.", bases),
+    };
+    let keys: Vec<String> = read_bulletin(title.clone(), Some(format!("{base}:keys")), None)
+        .try_into()
+        .unwrap();
+    keys.into_iter()
+        .map(|key| {
+            let full_tag = format!("{base}:[{key}]");
+            if !tags.contains(&full_tag) {
+                let found: Vec<_> = tags.iter().filter(|t| t.starts_with(&full_tag)).collect();
+                panic!("The value of {key} is not a single array: {found:?}.");
+            }
+            let obj = read_bulletin(title.clone(), Some(full_tag), None);
+            (key, obj)
+        })
+        .collect()
+}
+
+fn view_board() -> Vec<(String, String, u64)> {
+    let addr = lock(&SENDER).get_addr().clone();
+    let mut stream = TcpOrUnixStream::connect(addr).unwrap();
+    ciborium::into_writer(&Operation::ViewBoard, &mut stream).unwrap();
+    let board = ciborium::from_reader(&mut stream).unwrap();
+    stream.shutdown(std::net::Shutdown::Both).unwrap();
+    board
+}
@@ -0,0 +1,173 @@
+use array_object::adaptor::{VecShape, VecVecShape};
+use array_object::{ArrayObject, DataType};
+use num_complex::Complex;
+
+/// [Only for internal use] Reductions for `dbgbb_acc!(..., reduce => op, ...)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reduce {
+    Sum,
+    Mean,
+    Min,
+    Max,
+    /// Unbiased variance.
+    Var,
+    /// Number of the calls.
+    Count,
+}
+
+/// Element-wise running reduction.
+pub(crate) struct Reduction {
+    op: Reduce,
+    count: u64,
+    shape: Vec<usize>,
+    /// Sum, mean, min or max depending on the reduction.
+    values: Vec<f64>,
+    /// Sum of squared deviations for the variance.
+    m2: Vec<f64>,
+}
+
+impl Reduction {
+    pub(crate) fn new(op: Reduce) -> Self {
+        Self {
+            op,
+            count: 0,
+            shape: vec![],
+            values: vec![],
+            m2: vec![],
+        }
+    }
+    pub(crate) fn count(&self) -> u64 {
+        self.count
+    }
+    pub(crate) fn bytes(&self) -> usize {
+        8 * (self.values.len() + self.m2.len())
+    }
+    /// Adds the values of the shape. A change of the shape restarts the reduction except for the count of the calls.
+    pub(crate) fn update(&mut self, x: Vec<f64>, shape: Vec<usize>) {
+        if self.op == Reduce::Count {
+            self.count += 1;
+            return;
+        }
+        if self.count == 0 || self.shape != shape {
+            self.shape = shape;
+            self.values = x;
+            self.m2 = match self.op {
+                Reduce::Var => vec![0.0; self.values.len()],
+                _ => vec![],
+            };
+            self.count = 1;
+            return;
+        }
+        self.count += 1;
+        let n = self.count as f64;
+        match self.op {
+            Reduce::Sum => self.values.iter_mut().zip(x).for_each(|(s, x)| *s += x),
+            Reduce::Min => self
+                .values
+                .iter_mut()
+                .zip(x)
+                .for_each(|(m, x)| *m = m.min(x)),
+            Reduce::Max => self
+                .values
+                .iter_mut()
+                .zip(x)
+                .for_each(|(m, x)| *m = m.max(x)),
+            Reduce::Mean => self
+                .values
+                .iter_mut()
+                .zip(x)
+                .for_each(|(mean, x)| *mean += (x - *mean) / n),
+            Reduce::Var => {
+                for ((mean, m2), x) in self.values.iter_mut().zip(self.m2.iter_mut()).zip(x) {
+                    let delta = x - *mean;
+                    *mean += delta / n;
+                    *m2 += delta * (x - *mean);
+                }
+            }
+            Reduce::Count => {}
+        }
+    }
+    /// Combines two reductions of the same operation and shape. Otherwise, the other one replaces it.
+    pub(crate) fn merge(&mut self, other: Reduction) {
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 {
+            *self = other;
+            return;
+        }
+        if self.op != other.op || (self.op != Reduce::Count && self.shape != other.shape) {
+            *self = other;
+            return;
+        }
+        let (na, nb) = (self.count as f64, other.count as f64);
+        let n = na + nb;
+        let values = self.values.iter_mut().zip(other.values);
+        match self.op {
+            Reduce::Sum => values.for_each(|(a, b)| *a += b),
+            Reduce::Min => values.for_each(|(a, b)| *a = a.min(b)),
+            Reduce::Max => values.for_each(|(a, b)| *a = a.max(b)),
+            Reduce::Mean => values.for_each(|(a, b)| *a += (b - *a) * nb / n),
+            Reduce::Var => {
+                for ((a, b), (m2a, m2b)) in values.zip(self.m2.iter_mut().zip(other.m2)) {
+                    let delta = b - *a;
+                    *a += delta * nb / n;
+                    *m2a += m2b + delta * delta * na * nb / n;
+                }
+            }
+            Reduce::Count => {}
+        }
+        self.count += other.count;
+    }
+    /// Returns the reduced array. Returns None if nothing has been accumulated.
+    pub(crate) fn result(&self) -> Option<ArrayObject> {
+        if self.count == 0 {
+            return None;
+        }
+        let data = match self.op {
+            Reduce::Count => return Some(self.count.into()),
+            Reduce::Var if self.count < 2 => vec![f64::NAN; self.m2.len()],
+            Reduce::Var => {
+                let n = self.count as f64;
+                self.m2.iter().map(|m2| m2 / (n - 1.0)).collect()
+            }
+            _ => self.values.clone(),
+        };
+        if self.shape.is_empty() {
+            Some(data[0].into())
+        } else {
+            let shape = self.shape.iter().map(|&x| x as u64).collect();
+            Some(VecShape(data, shape).try_into().unwrap())
+        }
+    }
+}
+
+/// Returns the values of integers and real numbers. Complex numbers are not regarded as reals.
+pub(crate) fn to_reals(obj: &ArrayObject) -> Option<Vec<f64>> {
+    match obj.datatype() {
+        DataType::Complex => None,
+        _ => to_floats(obj),
+    }
+}
+
+/// Returns the values as floats. Complex numbers give the real parts followed by the imaginary parts.
+pub(crate) fn to_floats(obj: &ArrayObject) -> Option<Vec<f64>> {
+    let obj = obj.clone();
+    match (obj.datatype(), obj.dimension()) {
+        (DataType::Real, 0) => f64::try_from(obj).ok().map(|x| vec![x]),
+        (DataType::Real, _) => VecShape::<f64>::try_from(obj).ok().map(|v| v.0),
+        (DataType::Complex, 0) => Complex::<f64>::try_from(obj).ok().map(|x| vec![x.re, x.im]),
+        (DataType::Complex, _) => VecVecShape::<f64>::try_from(obj)
+            .ok()
+            .map(|v| v.0.into_iter().chain(v.1).collect()),
+        (DataType::SignedInteger, 0) => i64::try_from(obj).ok().map(|x| vec![x as f64]),
+        (DataType::SignedInteger, _) => VecShape::<i64>::try_from(obj)
+            .ok()
+            .map(|v| v.0.into_iter().map(|x| x as f64).collect()),
+        (DataType::UnsignedInteger, 0) => u64::try_from(obj).ok().map(|x| vec![x as f64]),
+        (DataType::UnsignedInteger, _) => VecShape::<u64>::try_from(obj)
+            .ok()
+            .map(|v| v.0.into_iter().map(|x| x as f64).collect()),
+        _ => None,
+    }
+}
@@ -1,4 +1,8 @@
 use num_complex::Complex;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::rc::Rc;
+use std::sync::Arc;
 
 /// Rename the variable.
 pub trait Rename {
@@ -27,6 +31,7 @@ macro_rules! impl_rename {
 }
 
 impl_rename!(
+    bool,
     u8,
     u16,
     u32,
@@ -46,13 +51,48 @@ impl_rename!(
     &str
 );
 
+impl<T> Rename for [T] {}
+
 impl<T: Clone> Rename for Vec<T> {}
 
+impl<T> Rename for VecDeque<T> {}
+
+impl<T: ?Sized> Rename for Box<T> {}
+
+impl<T: ?Sized> Rename for Rc<T> {}
+
+impl<T: ?Sized> Rename for Arc<T> {}
+
+impl<B: ToOwned + ?Sized> Rename for Cow<'_, B> {}
+
 impl<T: Clone, const N: usize> Rename for [T; N] {}
 
+impl<T> Rename for Option<T> {}
+
+impl<T, E> Rename for Result<T, E> {}
+
+impl<K, V, S> Rename for HashMap<K, V, S> {}
+
+impl<K, V> Rename for BTreeMap<K, V> {}
+
+macro_rules! impl_rename_tuple {
+    ($($name:ident),+) => {
+        impl<$($name),+> Rename for ($($name,)+) {}
+    }
+}
+
+impl_rename_tuple!(A);
+impl_rename_tuple!(A, B);
+impl_rename_tuple!(A, B, C);
+impl_rename_tuple!(A, B, C, D);
+impl_rename_tuple!(A, B, C, D, E);
+impl_rename_tuple!(A, B, C, D, E, F);
+impl_rename_tuple!(A, B, C, D, E, F, G);
+impl_rename_tuple!(A, B, C, D, E, F, G, H);
+
 pub struct Renamed<T> {
     name: String,
-    data: T,
+    pub(crate) data: T,
 }
 
 impl<T> Rename for Renamed<T> {
@@ -66,3 +106,10 @@ impl<T: Clone> Renamed<T> {
         self.data.clone()
     }
 }
+
+impl<T> Renamed<T> {
+    /// Returns the variable without the name.
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+}
@@ -0,0 +1,51 @@
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static SEED: AtomicU64 = AtomicU64::new(1);
+static OFFSET: LazyLock<u64> = LazyLock::new(|| {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+});
+
+/// [Only for internal use] Returns true if `count` is a power of `base`, i.e. 1, base, base^2, ...
+pub fn log_step(count: u64, base: u64) -> bool {
+    if count == 0 {
+        return false;
+    }
+    if base < 2 {
+        return true;
+    }
+    let mut rest = count;
+    while rest.is_multiple_of(base) {
+        rest /= base;
+    }
+    rest == 1
+}
+
+/// Returns the slot of the reservoir of size `size` to be overwritten by the `count`-th sample (counting from zero), or None if the sample is discarded.
+pub(crate) fn reservoir_slot(count: u64, size: usize) -> Option<usize> {
+    if count < size as u64 {
+        Some(count as usize)
+    } else {
+        let slot = random() % (count + 1);
+        if slot < size as u64 {
+            Some(slot as usize)
+        } else {
+            None
+        }
+    }
+}
+
+/// SplitMix64 seeded by the system time.
+fn random() -> u64 {
+    let mut z = SEED
+        .fetch_add(1, Ordering::Relaxed)
+        .wrapping_mul(0x9e3779b97f4a7c15)
+        .wrapping_add(*OFFSET);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
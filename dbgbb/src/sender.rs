@@ -3,12 +3,15 @@ use std::net::TcpStream as TcpOrUnixStream;
 #[cfg(feature = "unix")]
 use std::os::unix::net::UnixStream as TcpOrUnixStream;
 
-use crate::Operation;
+use crate::fork::forks;
+use crate::{Operation, lock};
 use array_object::{ArrayObject, Pack};
 use serde_bytes::ByteBuf;
-use std::io::{self, Cursor};
+use std::cell::RefCell;
+use std::io::{Cursor, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::mpsc::{self, Sender};
-use std::sync::{LazyLock, Mutex};
+use std::sync::{LazyLock, Mutex, PoisonError, RwLock};
 use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 
@@ -20,9 +23,9 @@ pub static SENDER: LazyLock<Mutex<BufferedSender>> =
 pub struct Buffer {}
 
 impl Buffer {
-    /// Enable the buffer.
+    /// Enable the buffer. In a forked child process, the buffer is restarted automatically.
     pub fn on() -> Self {
-        let mut sender = SENDER.lock().unwrap();
+        let mut sender = lock(&SENDER);
 
         let timeout = std::env::var("BB_TIMEOUT")
             .unwrap_or("3000".to_string())
@@ -37,7 +40,7 @@ impl Buffer {
     }
     /// Disable the buffer.
     pub fn off(&self) {
-        let mut sender = SENDER.lock().unwrap();
+        let mut sender = lock(&SENDER);
         sender.join();
     }
 }
@@ -50,26 +53,52 @@ impl Drop for Buffer {
 
 pub struct BufferedSender {
     addr: String,
+    forks: u64,
+    config: (u64, u64),
     handle: Option<(JoinHandle<()>, Sender<SenderControl>)>,
 }
 
 pub enum SenderControl {
-    Post((String, String, ArrayObject)),
+    Post(Vec<u8>),
     Shutdown,
 }
 
+/// Incremented whenever the buffer is turned on or off so that the threads refresh their cached channel.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Held for reading while a thread sends to its cached channel, and for writing while the buffer is turned off, so that no data is sent after the shutdown.
+static POSTING: RwLock<()> = RwLock::new(());
+
+/// Channel to the buffered sender cached by each thread.
+struct LocalSender {
+    generation: u64,
+    forks: u64,
+    addr: String,
+    tx: Option<Sender<SenderControl>>,
+}
+
+thread_local! {
+    static LOCAL: RefCell<Option<LocalSender>> = const { RefCell::new(None) };
+}
+
 impl BufferedSender {
     fn new() -> Self {
         #[cfg(not(feature = "unix"))]
         let addr = std::env::var("BB_ADDR").unwrap_or("127.0.0.1:7578".to_string());
         #[cfg(feature = "unix")]
         let addr = std::env::var("BB_ADDR").unwrap_or("/tmp/bb.sock".to_string());
-        Self { addr, handle: None }
+        Self {
+            addr,
+            forks: forks(),
+            config: (0, 0),
+            handle: None,
+        }
     }
     pub fn get_addr(&self) -> &String {
         &self.addr
     }
     fn start(&mut self, timeout: u64, interval: u64) {
+        self.config = (timeout, interval);
         let (tx, rx) = mpsc::channel::<SenderControl>();
         let addr = self.addr.clone();
         let handle = std::thread::spawn(move || {
@@ -78,20 +107,15 @@ impl BufferedSender {
             loop {
                 if let Ok(ctl) = rx.recv_timeout(Duration::from_millis(timeout)) {
                     match ctl {
-                        SenderControl::Post((title, tag, obj)) => {
-                            #[cfg(not(feature = "no_compression"))]
-                            let data = ByteBuf::from(obj.pack());
-                            #[cfg(feature = "no_compression")]
-                            let data = ByteBuf::from(obj.pack_as_it_is());
-                            ciborium::into_writer(&Operation::Post, &mut buffer).unwrap();
-                            ciborium::into_writer(&(title, tag, data), &mut buffer).unwrap();
+                        SenderControl::Post(data) => {
+                            buffer.write_all(&data).unwrap();
                         }
                         SenderControl::Shutdown => {
+                            while let Ok(SenderControl::Post(data)) = rx.try_recv() {
+                                buffer.write_all(&data).unwrap();
+                            }
                             if buffer.position() > 0 {
-                                let mut stream = TcpOrUnixStream::connect(&addr).unwrap();
-                                buffer.set_position(0);
-                                io::copy(&mut buffer, &mut stream).unwrap();
-                                stream.shutdown(std::net::Shutdown::Both).unwrap();
+                                send_direct(&addr, buffer.get_ref()).unwrap();
                             }
                             break;
                         }
@@ -100,50 +124,127 @@ impl BufferedSender {
                 let now = Instant::now();
                 if buffer.position() > 0 && now - time > Duration::from_millis(interval) {
                     time = now;
-                    let mut stream = TcpOrUnixStream::connect(&addr).unwrap();
-                    buffer.set_position(0);
-                    io::copy(&mut buffer, &mut stream).unwrap();
-                    stream.shutdown(std::net::Shutdown::Both).unwrap();
+                    send_direct(&addr, buffer.get_ref()).unwrap();
                     buffer = Cursor::new(vec![]);
                 }
             }
         });
         self.handle = Some((handle, tx));
+        GENERATION.fetch_add(1, Ordering::Release);
     }
-    pub fn post(
-        &self,
-        objs: Vec<(String, String, ArrayObject)>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        match &self.handle {
-            Some((_, tx)) => {
-                for obj in objs {
-                    tx.send(SenderControl::Post(obj))?;
-                }
-            }
-            None => {
-                if !objs.is_empty() {
-                    let mut buffer = Cursor::new(vec![]);
-                    for (title, tag, obj) in objs {
-                        #[cfg(not(feature = "no_compression"))]
-                        let data = ByteBuf::from(obj.pack());
-                        #[cfg(feature = "no_compression")]
-                        let data = ByteBuf::from(obj.pack_as_it_is());
-                        ciborium::into_writer(&Operation::Post, &mut buffer).unwrap();
-                        ciborium::into_writer(&(title, tag, data), &mut buffer).unwrap();
-                    }
-                    buffer.set_position(0);
-                    let mut stream = TcpOrUnixStream::connect(&self.addr)?;
-                    io::copy(&mut buffer, &mut stream)?;
-                    stream.shutdown(std::net::Shutdown::Both).unwrap();
-                }
+    fn join(&mut self) {
+        if self.forget_if_forked(forks()) {
+            return;
+        }
+        if let Some((handle, tx)) = self.handle.take() {
+            {
+                let _posting = POSTING.write().unwrap_or_else(PoisonError::into_inner);
+                GENERATION.fetch_add(1, Ordering::Release);
+                // The sender thread may have died with a panic, which has been reported already.
+                let _ = tx.send(SenderControl::Shutdown);
             }
+            let _ = handle.join();
         }
-        Ok(())
     }
-    fn join(&mut self) {
-        if let Some((handle, tx)) = self.handle.take() {
-            tx.send(SenderControl::Shutdown).unwrap();
-            handle.join().unwrap();
+    /// Restarts the thread of the buffered sender, which does not exist in a forked child.
+    fn restart_if_forked(&mut self, forks: u64) {
+        if self.forget_if_forked(forks) {
+            self.start(self.config.0, self.config.1);
         }
     }
+    /// Forgets the thread of the buffered sender inherited from the parent process. Returns true if there was one.
+    fn forget_if_forked(&mut self, forks: u64) -> bool {
+        if self.forks == forks {
+            return false;
+        }
+        self.forks = forks;
+        // The thread does not exist in the child, and the data in the channel is sent by the parent.
+        self.handle.take().map(std::mem::forget).is_some()
+    }
+}
+
+/// [Only for internal use] Helper function for the macros. The data is encoded in the calling thread and handed to the buffered sender without locking `SENDER`.
+pub fn post(objs: Vec<(String, String, ArrayObject)>) -> Result<(), Box<dyn std::error::Error>> {
+    if objs.is_empty() {
+        return Ok(());
+    }
+    let mut objs = Some(objs);
+    LOCAL
+        .try_with(|local| {
+            let data = encode(objs.take().unwrap());
+            let mut local = local.borrow_mut();
+            post_local(&mut local, data)
+        })
+        // The thread-local state is gone while the thread exits.
+        .unwrap_or_else(|_| post_direct(objs.take().unwrap()))
+}
+
+/// Hands the data to the buffered sender through the channel cached by the thread.
+fn post_local(
+    local: &mut Option<LocalSender>,
+    data: Vec<u8>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    loop {
+        let forks = forks();
+        let generation = GENERATION.load(Ordering::Acquire);
+        if local
+            .as_ref()
+            .is_none_or(|local| local.generation != generation || local.forks != forks)
+        {
+            let mut sender = lock(&SENDER);
+            sender.restart_if_forked(forks);
+            let generation = GENERATION.load(Ordering::Acquire);
+            *local = Some(LocalSender {
+                generation,
+                forks,
+                addr: sender.addr.clone(),
+                tx: sender.handle.as_ref().map(|(_, tx)| tx.clone()),
+            });
+        }
+        let local = local.as_ref().unwrap();
+        let Some(tx) = &local.tx else {
+            return send_direct(&local.addr, &data);
+        };
+        let _posting = POSTING.read().unwrap_or_else(PoisonError::into_inner);
+        // The buffer has been turned off since the channel was cached.
+        if GENERATION.load(Ordering::Acquire) != local.generation {
+            continue;
+        }
+        return match tx.send(SenderControl::Post(data)) {
+            Ok(()) => Ok(()),
+            Err(mpsc::SendError(SenderControl::Post(data))) => send_direct(&local.addr, &data),
+            Err(_) => Ok(()),
+        };
+    }
+}
+
+/// Sends the data without the thread-local state, which may be gone at exit.
+pub(crate) fn post_direct(
+    objs: Vec<(String, String, ArrayObject)>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if objs.is_empty() {
+        return Ok(());
+    }
+    let addr = lock(&SENDER).addr.clone();
+    send_direct(&addr, &encode(objs))
+}
+
+fn encode(objs: Vec<(String, String, ArrayObject)>) -> Vec<u8> {
+    let mut buffer = vec![];
+    for (title, tag, obj) in objs {
+        #[cfg(not(feature = "no_compression"))]
+        let data = ByteBuf::from(obj.pack());
+        #[cfg(feature = "no_compression")]
+        let data = ByteBuf::from(obj.pack_as_it_is());
+        ciborium::into_writer(&Operation::Post, &mut buffer).unwrap();
+        ciborium::into_writer(&(title, tag, data), &mut buffer).unwrap();
+    }
+    buffer
+}
+
+fn send_direct(addr: &str, data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut stream = TcpOrUnixStream::connect(addr)?;
+    stream.write_all(data)?;
+    stream.shutdown(std::net::Shutdown::Both)?;
+    Ok(())
 }
@@ -0,0 +1,65 @@
+use crate::changed::has_changed;
+use crate::fork::forks;
+use crate::lock;
+use array_object::ArrayObject;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// [Only for internal use] State of a single call site of the macros.
+pub struct Site {
+    forks: AtomicU64,
+    count: AtomicU64,
+    last: Mutex<Vec<(String, ArrayObject)>>,
+}
+
+impl Site {
+    pub const fn new() -> Self {
+        Self {
+            forks: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+            last: Mutex::new(Vec::new()),
+        }
+    }
+    /// Counts the call and returns the number of the previous calls in this process.
+    pub fn tick(&self) -> u64 {
+        self.reset_if_forked();
+        self.count.fetch_add(1, Ordering::Relaxed)
+    }
+    /// Returns true if the value differs from the last one recorded under the title, and records it.
+    pub fn changed(&self, title: &str, obj: &ArrayObject, tol: f64) -> bool {
+        self.reset_if_forked();
+        let mut last = lock(&self.last);
+        match last.iter_mut().find(|(name, _)| name == title) {
+            Some((_, prev)) => {
+                if has_changed(prev, obj, tol) {
+                    *prev = obj.clone();
+                    true
+                } else {
+                    false
+                }
+            }
+            None => {
+                last.push((title.to_string(), obj.clone()));
+                true
+            }
+        }
+    }
+}
+
+impl Site {
+    /// The state inherited from the parent process is discarded in a forked child.
+    fn reset_if_forked(&self) {
+        let forks = forks();
+        if self.forks.load(Ordering::Relaxed) != forks {
+            self.forks.store(forks, Ordering::Relaxed);
+            self.count.store(0, Ordering::Relaxed);
+            lock(&self.last).clear();
+        }
+    }
+}
+
+impl Default for Site {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,41 @@
+use crate::accumulator::{push_acc, reduce_acc};
+use crate::reduce::Reduce;
+use std::time::Instant;
+
+/// Scope guard measuring the elapsed time until it is dropped.
+pub struct Timer {
+    label: &'static str,
+    tag: String,
+    reduce: Option<Reduce>,
+    start: Instant,
+}
+
+impl Timer {
+    /// [Only for internal use] Helper function for `dbgbb_time!(...)`.
+    #[doc(hidden)]
+    pub fn start(label: &'static str, tag: String, reduce: Option<Reduce>) -> Self {
+        Self {
+            label,
+            tag,
+            reduce,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Drop for Timer {
+    /// Accumulates the elapsed time in seconds under the label.
+    fn drop(&mut self) {
+        let secs = self.start.elapsed().as_secs_f64();
+        let key = (
+            self.label.to_string(),
+            self.label.to_string(),
+            std::mem::take(&mut self.tag),
+        );
+        let objs = vec![(key, secs.into())];
+        match self.reduce {
+            Some(op) => reduce_acc(objs, op),
+            None => push_acc(objs, None, None),
+        }
+    }
+}
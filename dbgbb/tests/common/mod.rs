@@ -0,0 +1,23 @@
+//! Helpers shared by the tests.
+
+use dbgbb::ArrayObject;
+
+/// Evaluates the macro posting the data, written as `posted!(dbgbb_...!(...))` on a single line, and returns its tag. The nested macro starts right after `posted!(`, eight columns to the right.
+macro_rules! posted {
+    ($post:expr) => {{
+        $post;
+        format!("{}:{}:{}", file!(), line!(), column!() + 8)
+    }};
+}
+pub(crate) use posted;
+
+/// Reads the data with the tag given at runtime.
+pub fn read_tagged<T>(title: &str, tag: String) -> T
+where
+    ArrayObject: TryInto<T>,
+    <ArrayObject as TryInto<T>>::Error: std::fmt::Debug,
+{
+    dbgbb::read_bulletin(title.to_string(), Some(tag), None)
+        .try_into()
+        .unwrap()
+}
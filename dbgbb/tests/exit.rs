@@ -0,0 +1,33 @@
+#![cfg(unix)]
+use dbgbb::*;
+
+unsafe extern "C" {
+    fn atexit(callback: extern "C" fn()) -> i32;
+    fn fork() -> i32;
+    fn waitpid(pid: i32, status: *mut i32, options: i32) -> i32;
+}
+
+extern "C" fn flush() {
+    post_all_acc();
+}
+
+#[test]
+fn exit_hook() {
+    // SAFETY: The test binary runs this single test, and the child only accumulates and exits.
+    let pid = unsafe { fork() };
+    assert!(pid >= 0, "fork failed");
+    if pid == 0 {
+        unsafe { atexit(flush) };
+        // The thread-local state of the sender is in use, and destroyed before the hook runs.
+        dbgbb!(0.rename("exit_start"));
+        for i in 0..3 {
+            dbgbb_acc!(label => "exit", i.rename("exit_hook"));
+        }
+        std::process::exit(0);
+    }
+    let mut status = 0;
+    unsafe { waitpid(pid, &mut status, 0) };
+    assert_eq!(status, 0);
+    let recv: Vec<i32> = dbgbb_read!("exit_hook");
+    assert_eq!(recv, vec![0, 1, 2]);
+}
@@ -1,7 +1,8 @@
 #[cfg(any(feature = "ndarray_15", feature = "ndarray_16"))]
 mod test_ndarray {
+    use array_object::adaptor::VecShape;
     use dbgbb::*;
-    use ndarray::Array2;
+    use ndarray::{Array2, Array3};
     #[cfg(feature = "ndarray_15")]
     use ndarray_15 as ndarray;
     #[cfg(feature = "ndarray_16")]
@@ -10,10 +11,57 @@ mod test_ndarray {
     fn ndarray_integer() {
         let v: Vec<_> = (-128..128).map(|i| i as i32).collect();
         let ndarr = Array2::from_shape_vec((16, 16), v).unwrap();
-        dbgbb!(ndarr);
+        dbgbb!(&ndarr);
         let ndarr_recv: Array2<i32> = dbgbb_read!("ndarr");
         assert_eq!(ndarr, ndarr_recv);
     }
+    #[test]
+    fn ndarray_axes() {
+        let arr3 = Array3::from_shape_fn((2, 3, 4), |(i, j, k)| (100 * i + 10 * j + k) as u32);
+        dbgbb_index!(arr3, axes => [0, 2]);
+        let lane: Vec<u32> = dbgbb_read!("arr3", "tests/external.rs:21:9:[1,3]");
+        assert_eq!(lane, vec![103, 113, 123]);
+        dbgbb_flatten!(arr3.rename("arr3_flat"), axis => 0);
+        let VecShape::<u32>(v, shape) = dbgbb_read!("arr3_flat");
+        assert_eq!(v[..4], [100, 101, 102, 103]);
+        assert_eq!(shape, vec![3, 4]);
+    }
+    #[test]
+    fn ndarray_mask() {
+        let cells = Array2::from_shape_fn((2, 3), |(i, j)| i == j);
+        dbgbb!(&cells);
+        let recv: Vec<bool> = dbgbb_read!("cells", mask);
+        assert_eq!(recv, vec![true, false, false, false, true, false]);
+    }
+    #[test]
+    fn ndarray_views() {
+        let grid = Array2::from_shape_fn((3, 4), |(i, j)| (10 * i + j) as i32);
+        let col = grid.slice(ndarray::s![.., 1..;2]);
+        dbgbb!(col);
+        let VecShape::<i32>(v, shape) = dbgbb_read!("col");
+        assert_eq!(v, vec![1, 3, 11, 13, 21, 23]);
+        assert_eq!(shape, vec![3, 2]);
+        dbgbb!(grid.t().rename("grid_t"));
+        let transposed: Array2<i32> = dbgbb_read!("grid_t");
+        assert_eq!(transposed, grid.t());
+        let shared = grid.to_shared();
+        dbgbb!(&shared);
+        let recv: Array2<i32> = dbgbb_read!("shared");
+        assert_eq!(recv, grid);
+        let mut owned = grid.clone();
+        let mut view_mut = owned.view_mut();
+        view_mut[[0, 0]] = -1;
+        dbgbb!(&view_mut);
+        let recv: Array2<i32> = dbgbb_read!("view_mut");
+        assert_eq!(recv, view_mut);
+        let cow_grid = ndarray::CowArray::from(grid.slice(ndarray::s![.., 1..;2]));
+        dbgbb!(&cow_grid);
+        let recv: Array2<i32> = dbgbb_read!("cow_grid");
+        assert_eq!(recv, col);
+        dbgbb_flatten!(cow_grid.rename("cow_cols"), axis => 1);
+        let last: Vec<i32> = dbgbb_read!("cow_cols");
+        assert_eq!(last, vec![3, 13, 23]);
+    }
 }
 
 #[cfg(feature = "nalgebra")]
@@ -24,7 +72,7 @@ mod test_nalgebra {
     fn nalgebra_integer() {
         let v: Vec<_> = (-128..128).map(|i| i as i32).collect();
         let nalg = DMatrix::from_vec(16, 16, v);
-        dbgbb!(nalg);
+        dbgbb!(&nalg);
         let nalg_recv: DMatrix<i32> = dbgbb_read!("nalg");
         assert_eq!(nalg, nalg_recv);
     }
@@ -0,0 +1,60 @@
+#![cfg(unix)]
+mod common;
+
+use common::{posted, read_tagged};
+use dbgbb::*;
+
+unsafe extern "C" {
+    fn fork() -> i32;
+    fn waitpid(pid: i32, status: *mut i32, options: i32) -> i32;
+}
+
+#[test]
+fn forked_child() {
+    // The counts [0, 1] are posted before the fork, and [1, 0] remain in the parent.
+    let mut tag = String::new();
+    for x in [0.75f64, 0.25] {
+        tag = posted!(dbgbb_hist!(x.rename("fork_hist"), bins => 2, range => (0., 1.)));
+        if x > 0.5 {
+            dbgbb_hist!(post);
+        }
+    }
+    let buf = Buffer::on();
+    dbgbb!(0.rename("fork_parent"));
+    // SAFETY: The test binary runs this single test, and the child only posts and exits.
+    let pid = unsafe { fork() };
+    assert!(pid >= 0, "fork failed");
+    if pid == 0 {
+        for i in 0..3 {
+            dbgbb!(i.rename("fork_child"));
+        }
+        buf.off();
+        // The histogram of the parent must not be sent at exit.
+        std::process::exit(0);
+    }
+    let mut status = 0;
+    unsafe { waitpid(pid, &mut status, 0) };
+    assert_eq!(status, 0);
+    let child: i32 = dbgbb_read!("fork_child");
+    assert_eq!(child, 2);
+    let counts: Vec<u64> = read_tagged("fork_hist", format!("{tag}:counts"));
+    assert_eq!(counts, vec![0, 1]);
+    // SAFETY: As above. This child turns off the inherited buffer before posting anything.
+    let pid = unsafe { fork() };
+    assert!(pid >= 0, "fork failed");
+    if pid == 0 {
+        // The test harness of the child would catch a panic and exit successfully.
+        let off = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(buf)));
+        dbgbb!(1.rename("fork_dropped"));
+        std::process::exit(off.is_err() as i32);
+    }
+    let mut status = 0;
+    unsafe { waitpid(pid, &mut status, 0) };
+    assert_eq!(status, 0);
+    let dropped: i32 = dbgbb_read!("fork_dropped");
+    assert_eq!(dropped, 1);
+    dbgbb!(1.rename("fork_after"));
+    buf.off();
+    let parent: i32 = dbgbb_read!("fork_after");
+    assert_eq!(parent, 1);
+}
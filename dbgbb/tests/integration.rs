@@ -1,4 +1,7 @@
+mod common;
+
 use array_object::adaptor::VecShape;
+use common::{posted, read_tagged};
 use dbgbb::*;
 use num_complex::Complex64;
 
@@ -25,6 +28,19 @@ fn array() {
     post_read!(vec![1u32, 2u32, 3u32], "vec![1u32, 2u32, 3u32]", Vec<u32>);
 }
 
+#[test]
+fn pass_through() {
+    let through = vec![1f64, 2., 3.];
+    let w = dbgbb!(&through).clone();
+    let sum: f64 = dbgbb!(through.iter().sum::<f64>().rename("sum")).into_inner();
+    let (a, b) = dbgbb!(every => 2, w, 2 * 3);
+    assert_eq!(sum, 6.);
+    assert_eq!(a, through);
+    assert_eq!(b, 6);
+    let recv: Vec<f64> = dbgbb_read!("through");
+    assert_eq!(recv, through);
+}
+
 #[test]
 fn accumulate() {
     let mut c = vec![];
@@ -37,6 +53,205 @@ fn accumulate() {
     assert_eq!(res, c)
 }
 
+#[test]
+fn logevery() {
+    for i in 1..=20 {
+        dbgbb!(logevery => 2, i.rename("logevery"));
+    }
+    let last: i32 = dbgbb_read!("logevery");
+    assert_eq!(last, 16);
+}
+
+#[test]
+fn keep_last() {
+    for i in 0..100 {
+        dbgbb_acc!(label => "keep_last", keep_last => 5, i.rename("last_five"));
+    }
+    dbgbb_acc!("keep_last" => post);
+    let res: Vec<i32> = dbgbb_read!("last_five");
+    assert_eq!(res, vec![95, 96, 97, 98, 99]);
+}
+
+#[test]
+fn cap() {
+    let mut tag = String::new();
+    for i in 0..10 {
+        tag = posted!(dbgbb_acc!(label => "cap", cap => 16, i.rename("capped")));
+    }
+    dbgbb_acc!("cap" => post);
+    let chunk: Vec<i32> = read_tagged("capped", format!("{tag}:chunk1"));
+    assert_eq!(chunk, vec![5, 6, 7, 8, 9]);
+}
+
+#[test]
+fn reservoir() {
+    for i in 0..100 {
+        dbgbb_acc!(label => "reservoir", reservoir => 8, i.rename("sampled"));
+    }
+    dbgbb_acc!("reservoir" => post);
+    let res: Vec<i32> = dbgbb_read!("sampled");
+    assert_eq!(res.len(), 8);
+    assert!(res.iter().all(|&i| (0..100).contains(&i)));
+}
+
+#[test]
+fn nonfinite() {
+    for i in 0..4 {
+        let x = vec![1f64, 1. / (2. - i as f64)];
+        dbgbb!(on_nonfinite => x, once, i.rename("nonfinite"));
+        dbgbb!(on_exceed => (x, 0.8), i.rename("exceed"));
+    }
+    let first: i32 = dbgbb_read!("nonfinite");
+    assert_eq!(first, 2);
+    let last: i32 = dbgbb_read!("exceed");
+    assert_eq!(last, 3);
+}
+
+#[test]
+fn changed() {
+    for i in 0..10 {
+        let b = 1. + i as f64 * 1e-12;
+        dbgbb!(changed => 0, (i / 5).rename("changed_exact"));
+        dbgbb!(changed => 1e-9, b.rename("changed_tol"));
+    }
+    let a: i32 = dbgbb_read!("changed_exact", rev => 1);
+    assert_eq!(a, 1);
+    let b: f64 = dbgbb_read!("changed_tol");
+    assert_eq!(b, 1.);
+}
+
+fn nested(i: i32) -> i32 {
+    dbgbb!(every => 1, i.rename("nested_inner"));
+    dbgbb_acc!(label => "nested", every => 1, i.rename("nested_acc"));
+    i
+}
+
+#[test]
+fn reentrant() {
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            std::thread::spawn(|| {
+                for i in 0..10 {
+                    dbgbb!(every => 2, nested(i).rename("nested_outer"));
+                    dbgbb_acc!(label => "nested", every => 2, nested(i));
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    dbgbb_acc!("nested" => post);
+    let inner: i32 = dbgbb_read!("nested_inner");
+    assert!((0..10).contains(&inner));
+}
+
+#[test]
+fn poisoned() {
+    let _ = std::thread::spawn(|| {
+        let _acc = dbgbb::DATA_ACC.lock();
+        let _sender = dbgbb::SENDER.lock();
+        panic!("poison the global state");
+    })
+    .join();
+    assert!(dbgbb::DATA_ACC.is_poisoned());
+    for i in 0..3 {
+        dbgbb_acc!(label => "poisoned", i.rename("after_poison"));
+    }
+    dbgbb_acc!("poisoned" => post);
+    let res: Vec<i32> = dbgbb_read!("after_poison");
+    assert_eq!(res, vec![0, 1, 2]);
+}
+
+#[test]
+fn ragged() {
+    let mut tag = String::new();
+    for i in 0..4 {
+        let mesh = vec![0f64; i + 1];
+        tag = posted!(dbgbb_acc!(label => "ragged", mesh));
+    }
+    dbgbb_acc!("ragged" => post);
+    let VecShape::<f64>(_, shape) = read_tagged("mesh", format!("{tag}:[3]"));
+    assert_eq!(shape, vec![4]);
+}
+
+#[test]
+fn manage_acc() {
+    for i in 0..3 {
+        dbgbb_acc!(label => "manage", i.rename("managed"));
+        dbgbb_acc!(label => "discard", i.rename("discarded"));
+    }
+    let pending: Vec<_> = dbgbb::pending_acc()
+        .into_iter()
+        .filter(|p| p.label == "manage")
+        .collect();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].entries, 3);
+    assert_eq!(pending[0].bytes, 12);
+    dbgbb::clear_acc("discard");
+    dbgbb::rename_acc("manage", "managed");
+    assert!(
+        dbgbb::pending_acc()
+            .iter()
+            .all(|p| p.label != "manage" && p.label != "discard")
+    );
+    dbgbb::post_acc("managed");
+    let res: Vec<i32> = dbgbb_read!("managed");
+    assert_eq!(res, vec![0, 1, 2]);
+}
+
+#[test]
+fn reduce() {
+    for i in 0..5 {
+        let x = vec![i as f64, -(i as f64)];
+        dbgbb_acc!(label => "reduce", reduce => mean, x.rename("mean"));
+        dbgbb_acc!(label => "reduce", reduce => var, x.rename("var"));
+        dbgbb_acc!(label => "reduce", reduce => max, i.rename("max"));
+        dbgbb_acc!(label => "reduce", reduce => count, i.rename("count"));
+    }
+    dbgbb_acc!("reduce" => post);
+    let mean: Vec<f64> = dbgbb_read!("mean");
+    assert_eq!(mean, vec![2., -2.]);
+    let var: Vec<f64> = dbgbb_read!("var");
+    assert_eq!(var, vec![2.5, 2.5]);
+    let max: f64 = dbgbb_read!("max");
+    assert_eq!(max, 4.);
+    let count: u64 = dbgbb_read!("count");
+    assert_eq!(count, 5);
+}
+
+#[test]
+fn hist() {
+    let mut tag = String::new();
+    for i in 0..10 {
+        let x = vec![i as f64 - 2.0, f64::NAN];
+        tag = posted!(dbgbb_hist!(x.rename("hist"), bins => 5, range => (0, 5)));
+    }
+    dbgbb_hist!(post);
+    let edges: Vec<f64> = read_tagged("hist", format!("{tag}:edges"));
+    assert_eq!(edges, vec![0., 1., 2., 3., 4., 5.]);
+    let counts: Vec<u64> = read_tagged("hist", format!("{tag}:counts"));
+    assert_eq!(counts, vec![1, 1, 1, 1, 2]);
+    let outliers: Vec<u64> = read_tagged("hist", format!("{tag}:outliers"));
+    assert_eq!(outliers, vec![2, 2]);
+}
+
+#[test]
+fn time() {
+    for i in 0..3 {
+        let x: i32 = dbgbb_time!("time", { i * 2 });
+        assert_eq!(x, i * 2);
+        let _timer = dbgbb_time!("time_count", reduce => count);
+    }
+    dbgbb_acc!("time" => post);
+    dbgbb_acc!("time_count" => post);
+    let durations: Vec<f64> = dbgbb_read!("time");
+    assert_eq!(durations.len(), 3);
+    assert!(durations.iter().all(|&t| t >= 0.));
+    let count: u64 = dbgbb_read!("time_count");
+    assert_eq!(count, 3);
+}
+
 #[test]
 fn flatten() {
     let vv = vec![vec![1, 2], vec![3, 4]];
@@ -57,6 +272,22 @@ fn concat() {
     assert_eq!(shape, vec![2, 2]);
 }
 
+#[test]
+fn deep() {
+    let v5 = vec![vec![vec![vec![vec![1u8, 2]]]]; 2];
+    let tag = posted!(dbgbb_index!(v5, depth => all));
+    let last: u8 = read_tagged("v5", format!("{tag}:[1,0,0,0,1]"));
+    assert_eq!(last, 2);
+    let depth = 4;
+    dbgbb_flatten!(v5.rename("v5_4"), depth => depth);
+    let flat: Vec<u8> = dbgbb_read!("v5_4");
+    assert_eq!(flat, vec![1, 2]);
+    dbgbb_concat!(v5.rename("v5_cat"), depth => all);
+    let VecShape::<u8>(v, shape) = dbgbb_read!("v5_cat");
+    assert_eq!(v, vec![1, 2, 1, 2]);
+    assert_eq!(shape, vec![2, 1, 1, 1, 2]);
+}
+
 #[test]
 fn buffer() {
     let _buf = dbgbb::Buffer::on();
@@ -64,3 +295,357 @@ fn buffer() {
         dbgbb!(vec![1f64; 100].rename(&i.to_string()));
     }
 }
+
+#[derive(Clone, DbgBB)]
+struct Velocity(f64, #[dbgbb(rename = "y")] f64);
+
+#[derive(Clone, DbgBB)]
+struct State {
+    step: u64,
+    pos: Vec<f64>,
+    vel: Velocity,
+    #[dbgbb(rename = "temperature")]
+    temp: f64,
+    #[dbgbb(skip)]
+    _cache: std::collections::HashSet<u8>,
+}
+
+#[test]
+fn derive() {
+    let derived = State {
+        step: 3,
+        pos: vec![1., 2.],
+        vel: Velocity(0.5, 0.),
+        temp: 300.,
+        _cache: Default::default(),
+    };
+    dbgbb!(&derived);
+    let step: u64 = dbgbb_read!("derived.step");
+    assert_eq!(step, 3);
+    let pos: Vec<f64> = dbgbb_read!("derived.pos");
+    assert_eq!(pos, vec![1., 2.]);
+    let vel: f64 = dbgbb_read!("derived.vel.0");
+    assert_eq!(vel, 0.5);
+    let vel_y: f64 = dbgbb_read!("derived.vel.y");
+    assert_eq!(vel_y, 0.);
+    let temp: f64 = dbgbb_read!("derived.temperature");
+    assert_eq!(temp, 300.);
+    for _ in 0..2 {
+        dbgbb_acc!(label => "derive", derived.rename("acc_state"));
+    }
+    dbgbb_acc!("derive" => post);
+    let steps: Vec<u64> = dbgbb_read!("acc_state.step");
+    assert_eq!(steps, vec![3, 3]);
+}
+
+#[derive(Clone, DbgBB)]
+struct Particle {
+    pos: [f64; 3],
+    mass: f64,
+}
+
+#[test]
+fn struct_of_arrays() {
+    let particles: Vec<_> = (0..4)
+        .map(|i| Particle {
+            pos: [i as f64; 3],
+            mass: 1.,
+        })
+        .collect();
+    dbgbb!(&particles);
+    let VecShape::<f64>(pos, shape) = dbgbb_read!("particles.pos");
+    assert_eq!(shape, vec![4, 3]);
+    assert_eq!(pos[3..6], [1., 1., 1.]);
+    let mass: Vec<f64> = dbgbb_read!("particles.mass");
+    assert_eq!(mass, vec![1.; 4]);
+}
+
+#[test]
+fn tuple() {
+    let pair = (1.5f64, 2u32);
+    dbgbb!(&pair);
+    let first: f64 = dbgbb_read!("pair.0");
+    assert_eq!(first, 1.5);
+    let second: u64 = dbgbb_read!("pair.1");
+    assert_eq!(second, 2);
+    let xy: Vec<(f64, f64)> = (0..3).map(|i| (i as f64, 2. * i as f64)).collect();
+    dbgbb!(&xy);
+    let VecShape::<f64>(v, shape) = dbgbb_read!("xy");
+    assert_eq!(shape, vec![3, 2]);
+    assert_eq!(v, vec![0., 0., 1., 2., 2., 4.]);
+    let points = vec![[1f64, 2., 3.]; 4];
+    dbgbb!(&points);
+    let VecShape::<f64>(_, shape) = dbgbb_read!("points");
+    assert_eq!(shape, vec![4, 3]);
+    let mixed = vec![(1f64, 2i32); 2];
+    dbgbb!(&mixed);
+    let ints: Vec<i64> = dbgbb_read!("mixed.1");
+    assert_eq!(ints, vec![2, 2]);
+}
+
+#[test]
+fn map() {
+    use std::collections::{BTreeMap, HashMap};
+    let residuals = HashMap::from([("u".to_string(), 1e-3f64), ("p".to_string(), 2e-4)]);
+    dbgbb!(&residuals);
+    let recv: HashMap<String, f64> = dbgbb_read!("residuals", map);
+    assert_eq!(recv, residuals);
+    let by_id = BTreeMap::from([(1u32, vec![1i32, 2]), (7, vec![3, 4])]);
+    dbgbb!(&by_id);
+    let recv: BTreeMap<u32, Vec<i32>> = dbgbb_read!("by_id", map);
+    assert_eq!(recv, by_id);
+}
+
+#[test]
+fn option() {
+    let fit: Option<Vec<f64>> = Some(vec![1.0, 2.0]);
+    dbgbb!(fit);
+    let recv: Vec<f64> = dbgbb_read!("fit");
+    assert_eq!(recv, vec![1.0, 2.0]);
+    let gap: Option<f64> = None;
+    dbgbb!(gap);
+    let marker: String = dbgbb_read!("gap");
+    assert_eq!(marker, "None");
+    let solve_result: Result<f64, String> = Err("diverged".to_string());
+    dbgbb!(&solve_result);
+    let marker: String = dbgbb_read!("solve_result");
+    assert_eq!(marker, "\"diverged\"");
+}
+
+#[test]
+fn mask() {
+    let active = vec![true, false, false, true];
+    dbgbb!(&active);
+    let recv: Vec<bool> = dbgbb_read!("active", mask);
+    assert_eq!(recv, active);
+    let converged = [false, true];
+    dbgbb!(converged);
+    let bytes: Vec<u8> = dbgbb_read!("converged");
+    assert_eq!(bytes, vec![0, 1]);
+}
+
+#[test]
+fn containers() {
+    use std::borrow::Cow;
+    use std::collections::VecDeque;
+    use std::sync::Arc;
+    let v: Vec<i32> = (0..30).collect();
+    dbgbb!(&v[10..20]);
+    let recv: Vec<i32> = dbgbb_read!("v[10..20]");
+    assert_eq!(recv, (10..20).collect::<Vec<_>>());
+    let mut history = VecDeque::from([1f64, 2., 3.]);
+    history.pop_front();
+    history.push_back(4.);
+    dbgbb!(&history);
+    let recv: Vec<f64> = dbgbb_read!("history");
+    assert_eq!(recv, vec![2., 3., 4.]);
+    let boxed: Box<[f32]> = vec![1f32, 2.].into_boxed_slice();
+    dbgbb!(boxed.rename("boxed_slice"));
+    let recv: Vec<f32> = dbgbb_read!("boxed_slice");
+    assert_eq!(recv, vec![1., 2.]);
+    let shared: Arc<[u8]> = Arc::from(vec![5u8, 6]);
+    let cow: Cow<[u8]> = Cow::Borrowed(&shared);
+    dbgbb!(&cow);
+    let recv: Vec<u8> = dbgbb_read!("cow");
+    assert_eq!(recv, vec![5, 6]);
+    let rows = VecDeque::from([Arc::new(vec![1u32, 2]), Arc::new(vec![3, 4])]);
+    let tag = posted!(dbgbb_index!(rows, depth => 1));
+    let recv: Vec<u32> = read_tagged("rows", format!("{tag}:[1]"));
+    assert_eq!(recv, vec![3, 4]);
+    let window = VecDeque::from([5f64, 6.]);
+    dbgbb_acc!(label => "buffers", &window);
+    dbgbb_acc!("buffers" => post);
+    let VecShape::<f64>(recv, shape) = dbgbb_read!("window");
+    assert_eq!((recv, shape), (vec![5., 6.], vec![1, 2]));
+}
+
+#[test]
+fn changed_variable() {
+    let changed = 5;
+    let b = 1;
+    let (c, d) = dbgbb!(changed, b);
+    assert_eq!((c, d), (5, 1));
+}
+
+#[test]
+fn acc_once() {
+    let mut calls = 0;
+    let mut next = || {
+        calls += 1;
+        calls
+    };
+    dbgbb_acc!(label => "once", next().rename("acc_once"));
+    dbgbb_acc!("once" => post);
+    assert_eq!(calls, 1);
+    let value: Vec<i32> = dbgbb_read!("acc_once");
+    assert_eq!(value, vec![1]);
+}
+
+#[test]
+fn reduce_reshape() {
+    for n in [2, 2, 3, 3] {
+        let x = vec![n as f64; n];
+        dbgbb_acc!(label => "reshape", reduce => mean, x.rename("reshape_mean"));
+    }
+    dbgbb_acc!("reshape" => post);
+    let mean: Vec<f64> = dbgbb_read!("reshape_mean");
+    assert_eq!(mean, vec![3.; 3]);
+}
+
+mod qualified {
+    #[test]
+    fn time_qualified() {
+        dbgbb::dbgbb_time!("qualified", reduce => mean, {});
+        dbgbb::post_acc("qualified");
+        let mean: f64 = dbgbb::dbgbb_read!("qualified");
+        assert!(mean >= 0.);
+    }
+}
+
+#[test]
+fn ragged_fields() {
+    let ragged_rows = vec![vec![1f64], vec![2., 3.]];
+    let tag = posted!(dbgbb!(&ragged_rows));
+    let second: Vec<f64> = read_tagged("ragged_rows", format!("{tag}:[1]"));
+    assert_eq!(second, vec![2., 3.]);
+}
+
+#[test]
+fn tuple_rows() {
+    let empty: Vec<(f64, f64)> = vec![];
+    dbgbb!(&empty);
+    let cloud: Vec<_> = (0..1000).map(|i| (i as f32, 2. * i as f32, 0f32)).collect();
+    dbgbb!(&cloud);
+    let VecShape::<f32>(v, shape) = dbgbb_read!("cloud");
+    assert_eq!(shape, vec![1000, 3]);
+    assert_eq!(v[3..6], [1., 2., 0.]);
+}
+
+#[test]
+fn map_removed() {
+    use std::collections::BTreeMap;
+    for n in [2, 1] {
+        let shrinking: BTreeMap<u32, i32> = (0..n).map(|i| (i, i as i32)).collect();
+        dbgbb!(&shrinking);
+    }
+    let recv: BTreeMap<u32, i32> = dbgbb_read!("shrinking", map);
+    assert_eq!(recv, BTreeMap::from([(0, 0)]));
+}
+
+#[test]
+#[should_panic(expected = "not a single array")]
+fn map_options() {
+    use std::collections::BTreeMap;
+    let optional = BTreeMap::from([(0, Some(1f64)), (1, None)]);
+    dbgbb!(&optional);
+    let _: BTreeMap<u32, f64> = dbgbb_read!("optional", map);
+}
+
+#[test]
+fn nested_refs() {
+    let pairs = vec![vec![1u8, 2], vec![3, 4]];
+    dbgbb_concat!(&pairs, depth => 1);
+    let VecShape::<u8>(recv, shape) = dbgbb_read!("pairs");
+    assert_eq!((recv, shape), (vec![1, 2, 3, 4], vec![2, 2]));
+    let last_pair = pairs.clone();
+    dbgbb_flatten!(&last_pair, depth => 1);
+    let recv: Vec<u8> = dbgbb_read!("last_pair");
+    assert_eq!(recv, vec![3, 4]);
+}
+
+#[test]
+fn thread_exit() {
+    struct Guard;
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            dbgbb!(1.rename("thread_exit"));
+        }
+    }
+    thread_local! {
+        static GUARD: Guard = const { Guard };
+    }
+    std::thread::spawn(|| {
+        // The guard is registered first and dropped after the thread-local state of the sender.
+        GUARD.with(|_| {});
+        dbgbb!(0.rename("thread_start"));
+    })
+    .join()
+    .unwrap();
+    let recv: i32 = dbgbb_read!("thread_exit");
+    assert_eq!(recv, 1);
+}
+
+#[test]
+fn reservoir_twice() {
+    for n in [1000, 100] {
+        for i in 0..n {
+            dbgbb_acc!(label => "twice", reservoir => 8, i.rename("reservoir_twice"));
+        }
+        let pending = pending_acc();
+        let entries = pending
+            .iter()
+            .find(|acc| acc.label == "twice")
+            .unwrap()
+            .entries;
+        assert_eq!(entries, 8);
+        dbgbb_acc!("twice" => post);
+        let recv: Vec<i32> = dbgbb_read!("reservoir_twice");
+        assert_eq!(recv.len(), 8);
+    }
+}
+
+#[test]
+fn io_result() {
+    let opened: Result<f64, std::io::Error> = Ok(1.5);
+    dbgbb!(&opened);
+    let recv: f64 = dbgbb_read!("opened");
+    assert_eq!(recv, 1.5);
+}
+
+#[test]
+fn derive_generic() {
+    #[derive(Clone, DbgBB)]
+    struct Pair<T> {
+        first: T,
+        second: T,
+    }
+    let pair = Pair {
+        first: 1.5f64,
+        second: 2.5,
+    };
+    dbgbb!(&pair);
+    let first: f64 = dbgbb_read!("pair.first");
+    let second: f64 = dbgbb_read!("pair.second");
+    assert_eq!((first, second), (1.5, 2.5));
+}
+
+#[test]
+fn reduce_count_reshape() {
+    for n in [2, 2, 3] {
+        let x = vec![Complex64::new(1., 0.); n];
+        dbgbb_acc!(label => "count_reshape", reduce => count, x.rename("reshape_count"));
+        dbgbb_acc!(label => "count_reshape", reduce => mean, x.rename("complex_mean"));
+    }
+    dbgbb_acc!("count_reshape" => post);
+    let count: u64 = dbgbb_read!("reshape_count");
+    assert_eq!(count, 3);
+    assert!(
+        std::panic::catch_unwind(|| {
+            let _: Vec<f64> = dbgbb_read!("complex_mean");
+        })
+        .is_err()
+    );
+}
+
+#[test]
+fn hist_once() {
+    let mut calls = 0;
+    let mut sample = || {
+        calls += 1;
+        0.5f64
+    };
+    for _ in 0..3 {
+        dbgbb_hist!(sample().rename("hist_once"), bins => 2, range => (0., 1.));
+    }
+    assert_eq!(calls, 3);
+}
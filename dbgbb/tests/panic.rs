@@ -0,0 +1,16 @@
+use dbgbb::*;
+
+#[test]
+fn post_on_panic() {
+    post_acc_on_panic();
+    let result = std::thread::spawn(|| {
+        for step in 0..10 {
+            dbgbb_acc!(label => "panic", keep_last => 3, step.rename("panic_last"));
+        }
+        panic!("diverged");
+    })
+    .join();
+    assert!(result.is_err());
+    let last: Vec<i32> = dbgbb_read!("panic_last");
+    assert_eq!(last, vec![7, 8, 9]);
+}